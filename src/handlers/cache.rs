@@ -6,7 +6,7 @@ use axum::{extract::State, response::Json};
     path = "/clear-cache",
     tag = "cache",
     summary = "清理所有缓存",
-    description = "清理Redis中的所有缓存条目，返回清理的条目数量",
+    description = "清理Redis中的所有缓存条目、过期副本、版本历史与版本固定，返回清理的条目数量",
     responses(
         (status = 200, description = "成功清理缓存", body = ClearCacheResponse),
         (status = 500, description = "内部服务器错误", body = crate::utils::errors::ErrorResponse)
@@ -0,0 +1,111 @@
+use crate::{
+    models::notifications::{ChangeEvent, WatchRequest, WatchResponse},
+    system::AppState,
+    utils::errors::{AppError, AppResult},
+};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        Json,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::{Stream, stream};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// 逗号分隔的配置文件路径，如 `/app/application.yml,/app/application-prod.yml`
+    paths: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "proxy",
+    summary = "订阅配置变更",
+    description = "通过 Server-Sent Events 订阅一个或多个配置文件的变更。内容变化时推送 `change` 事件，数据为 ChangeEvent；\
+        本地缓冲区溢出时推送 `lagged` 事件，客户端应重新拉取所有订阅的配置",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "事件流", body = ChangeEvent, content_type = "text/event-stream"),
+        (status = 400, description = "路径无效", body = crate::utils::errors::ErrorResponse),
+        (status = 404, description = "未启用变更通知", body = crate::utils::errors::ErrorResponse)
+    )
+)]
+pub async fn subscribe_changes(
+    Query(query): Query<EventsQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let notifier = app_state.change_notifier.clone();
+    if !notifier.enabled() {
+        return Err(AppError::NotFound(
+            "Change notifications are disabled".to_string(),
+        ));
+    }
+
+    // 订阅路径与事件路径统一为规范 yml 路径
+    let paths = query
+        .paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(|path| app_state.proxy_service.canonical_path_for(path))
+        .collect::<AppResult<HashSet<_>>>()?;
+    if paths.is_empty() {
+        return Err(AppError::BadRequest("paths is required".to_string()));
+    }
+
+    let receiver = notifier.subscribe();
+    let events = stream::unfold((receiver, paths), |(mut receiver, paths)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(change) if paths.contains(&change.path) => {
+                    match Event::default().event("change").json_data(&change) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            tracing::warn!("Failed to encode change event: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    Event::default().event("lagged").data(skipped.to_string())
+                }
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (receiver, paths)));
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(notifier.keep_alive())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/watch",
+    tag = "proxy",
+    summary = "长轮询监听配置变更",
+    description = "提交配置文件路径及客户端持有的版本哈希（首次可为空）。任一文件的当前版本与提交的哈希不一致时立即返回；\
+        否则阻塞到有文件变化或超时，超时返回空列表。返回的哈希用于下一次请求，vary 请求头需与拉取配置时一致",
+    request_body = WatchRequest,
+    responses(
+        (status = 200, description = "发生变化的配置文件", body = WatchResponse),
+        (status = 400, description = "路径无效或监听数量超出上限", body = crate::utils::errors::ErrorResponse),
+        (status = 404, description = "未启用变更通知", body = crate::utils::errors::ErrorResponse)
+    )
+)]
+pub async fn watch_changes(
+    headers: HeaderMap,
+    State(app_state): State<AppState>,
+    Json(request): Json<WatchRequest>,
+) -> AppResult<Json<WatchResponse>> {
+    let changed = app_state.watch_service.watch(request, &headers).await?;
+    Ok(Json(WatchResponse { changed }))
+}
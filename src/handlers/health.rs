@@ -6,11 +6,15 @@ use axum::{extract::State, response::Json};
     path = "/get-runtime",
     tag = "monitoring",
     summary = "获取运行时统计信息",
-    description = "返回服务的运行时统计信息，包括请求总数、缓存命中数和启动时间",
+    description = "返回服务的运行时统计信息，包括请求总数、缓存命中数、启动时间和上游熔断器状态",
     responses(
         (status = 200, description = "成功返回运行时信息", body = RuntimeInfo)
     )
 )]
 pub async fn get_runtime(State(app_state): State<AppState>) -> Json<RuntimeInfo> {
-    Json(app_state.runtime_stats.to_info())
+    let mut info = app_state.runtime_stats.to_info();
+    info.circuit_breaker = app_state.proxy_service.circuit_breaker_info();
+    info.upstream_circuit_breakers = app_state.proxy_service.upstream_circuit_breaker_infos();
+    info.offline = app_state.offline_mode.is_enabled();
+    Json(info)
 }
@@ -0,0 +1,217 @@
+use crate::{
+    models::history::{
+        CacheEntryInfo, PinInfo, PinRequest, StructuralDiff, UnpinResponse, VersionListResponse,
+    },
+    services::history_service::{DiffMode, VersionDiff},
+    system::{AdminAuth, AppState},
+    utils::errors::AppResult,
+};
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+pub struct HistoryQuery {
+    /// 配置文件请求路径，如 `/app/application-prod.yml`
+    pub path: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RemoveQuery {
+    /// 配置文件请求路径
+    pub path: String,
+    /// 操作原因，记录到审计日志
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DiffQuery {
+    /// 配置文件请求路径
+    path: String,
+    /// 旧版本哈希
+    from: String,
+    /// 新版本哈希，缺省时使用最新版本
+    to: Option<String>,
+    /// 差异格式：unified / structural
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    mode: DiffMode,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/history",
+    tag = "admin",
+    summary = "列出配置版本历史",
+    description = "列出指定配置文件最近的版本，按内容哈希去重并按时间倒序排列。vary 请求头的取值通过同名查询参数传入（如 `&x-tenant=acme`），与代理请求一致时才能定位到同一缓存键",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "成功返回版本列表", body = VersionListResponse),
+        (status = 401, description = "令牌缺失或错误", body = crate::utils::errors::ErrorResponse),
+        (status = 403, description = "未启用管理接口", body = crate::utils::errors::ErrorResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn list_versions(
+    _auth: AdminAuth,
+    Query(vary): Query<HashMap<String, String>>,
+    Query(query): Query<HistoryQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<VersionListResponse>> {
+    let headers = app_state.proxy_service.vary_headers(&vary)?;
+    let key = app_state
+        .proxy_service
+        .cache_key_for(&query.path, &headers)?;
+    Ok(Json(app_state.history_service.list(&key).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/history/diff",
+    tag = "admin",
+    summary = "对比两个配置版本",
+    description = "返回两个版本之间的差异：unified 为统一格式文本差异，structural 为按展平配置键列出的 JSON 变化列表",
+    params(DiffQuery),
+    responses(
+        (status = 200, description = "unified 模式返回文本差异", body = String),
+        (status = 200, description = "structural 模式返回结构化差异", body = StructuralDiff),
+        (status = 404, description = "版本不存在", body = crate::utils::errors::ErrorResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn diff_versions(
+    _auth: AdminAuth,
+    Query(vary): Query<HashMap<String, String>>,
+    Query(query): Query<DiffQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Response> {
+    let headers = app_state.proxy_service.vary_headers(&vary)?;
+    let key = app_state
+        .proxy_service
+        .cache_key_for(&query.path, &headers)?;
+    let diff = app_state
+        .history_service
+        .diff(&key, &query.from, query.to.as_deref(), query.mode)
+        .await?;
+
+    Ok(match diff {
+        VersionDiff::Unified(text) => {
+            ([(header::CONTENT_TYPE, "text/x-diff; charset=utf-8")], text).into_response()
+        }
+        VersionDiff::Structural(diff) => Json(diff).into_response(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/cache",
+    tag = "admin",
+    summary = "查看缓存条目",
+    description = "返回配置文件对应缓存键的剩余时间、当前内容哈希、生效中的紧急覆盖与版本固定，以及已记录的版本数",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "成功返回缓存条目信息", body = CacheEntryInfo)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn inspect_cache(
+    _auth: AdminAuth,
+    Query(vary): Query<HashMap<String, String>>,
+    Query(query): Query<HistoryQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<CacheEntryInfo>> {
+    let headers = app_state.proxy_service.vary_headers(&vary)?;
+    let key = app_state
+        .proxy_service
+        .cache_key_for(&query.path, &headers)?;
+    let canonical = app_state.proxy_service.canonical_path_for(&query.path)?;
+
+    let mut info = app_state.history_service.inspect(&key).await?;
+    info.active_override = app_state.override_service.get_info(&canonical).await?;
+    Ok(Json(info))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/pins",
+    tag = "admin",
+    summary = "列出版本固定",
+    description = "列出所有生效中的版本固定及其剩余时间",
+    responses(
+        (status = 200, description = "成功返回固定列表", body = Vec<PinInfo>)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn list_pins(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<Vec<PinInfo>>> {
+    Ok(Json(app_state.history_service.list_pins().await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/pins",
+    tag = "admin",
+    summary = "固定到历史版本",
+    description = "在指定时长内把配置文件固定到历史版本：直接返回该版本内容且不访问上游，用于快速回滚错误的配置发布",
+    request_body = PinRequest,
+    responses(
+        (status = 200, description = "固定成功", body = PinInfo),
+        (status = 400, description = "固定时长超出范围", body = crate::utils::errors::ErrorResponse),
+        (status = 404, description = "版本不存在", body = crate::utils::errors::ErrorResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn pin_version(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+    Json(request): Json<PinRequest>,
+) -> AppResult<Json<PinInfo>> {
+    let headers = app_state.proxy_service.vary_headers(&request.vary)?;
+    let key = app_state
+        .proxy_service
+        .cache_key_for(&request.path, &headers)?;
+    let canonical = app_state.proxy_service.canonical_path_for(&request.path)?;
+    Ok(Json(
+        app_state
+            .history_service
+            .pin(&key, &canonical, request)
+            .await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/pins",
+    tag = "admin",
+    summary = "解除版本固定",
+    description = "解除配置文件的版本固定，之后恢复正常的缓存与上游刷新",
+    params(RemoveQuery),
+    responses(
+        (status = 200, description = "操作完成", body = UnpinResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn unpin_version(
+    _auth: AdminAuth,
+    Query(vary): Query<HashMap<String, String>>,
+    Query(query): Query<RemoveQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<UnpinResponse>> {
+    let headers = app_state.proxy_service.vary_headers(&vary)?;
+    let key = app_state
+        .proxy_service
+        .cache_key_for(&query.path, &headers)?;
+    let canonical = app_state.proxy_service.canonical_path_for(&query.path)?;
+    let removed = app_state
+        .history_service
+        .unpin(&key, &canonical, query.reason.as_deref())
+        .await?;
+    Ok(Json(UnpinResponse { key, removed }))
+}
@@ -1,3 +1,8 @@
 pub mod health;
+pub mod history;
+pub mod monitor;
+pub mod offline;
+pub mod overrides;
 pub mod cache;
+pub mod events;
 pub mod proxy; 
\ No newline at end of file
@@ -0,0 +1,27 @@
+use crate::{models::responses::MonitorResponse, system::AppState, utils::errors::AppResult};
+use axum::{body::Bytes, extract::State, http::HeaderMap, response::Json};
+
+#[utoipa::path(
+    post,
+    path = "/monitor",
+    tag = "cache",
+    summary = "接收配置仓库推送回调",
+    description = "接收 GitHub、GitLab 与 Gitea 的 push 回调并校验签名（GitLab 校验 Secret token），\
+        把变化的文件映射为应用名与 profile，只失效受影响的缓存键；开启 webhook.refresh 时立即从上游重新拉取。离线模式下不失效任何缓存",
+    request_body(content = String, description = "平台原始推送负载", content_type = "application/json"),
+    responses(
+        (status = 200, description = "处理完成", body = MonitorResponse),
+        (status = 400, description = "不支持的来源或负载无效", body = crate::utils::errors::ErrorResponse),
+        (status = 401, description = "签名校验失败", body = crate::utils::errors::ErrorResponse),
+        (status = 403, description = "未配置回调密钥", body = crate::utils::errors::ErrorResponse)
+    )
+)]
+pub async fn receive_push(
+    headers: HeaderMap,
+    State(app_state): State<AppState>,
+    body: Bytes,
+) -> AppResult<Json<MonitorResponse>> {
+    Ok(Json(
+        app_state.monitor_service.handle(&headers, &body).await?,
+    ))
+}
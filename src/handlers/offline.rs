@@ -0,0 +1,46 @@
+use crate::{
+    models::runtime::{OfflineRequest, OfflineStatus},
+    system::{AdminAuth, AppState},
+    utils::errors::AppResult,
+};
+use axum::{extract::State, response::Json};
+
+#[utoipa::path(
+    get,
+    path = "/admin/offline",
+    tag = "admin",
+    summary = "查看离线模式",
+    description = "返回当前是否处于离线（只读）模式、配置文件中的默认值以及最近一次切换的原因",
+    responses(
+        (status = 200, description = "成功返回离线模式状态", body = OfflineStatus)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn get_offline(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<OfflineStatus>> {
+    Ok(Json(app_state.offline_mode.status().await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/offline",
+    tag = "admin",
+    summary = "切换离线模式",
+    description = "进入离线模式后只从缓存、过期副本与磁盘快照返回配置，从不访问上游，缓存条目不再过期；退出时恢复过期时间。\
+        切换结果保存在 Redis 中，所有副本在数秒内同步",
+    request_body = OfflineRequest,
+    responses(
+        (status = 200, description = "切换成功", body = OfflineStatus),
+        (status = 400, description = "缺少切换原因", body = crate::utils::errors::ErrorResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn set_offline(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+    Json(request): Json<OfflineRequest>,
+) -> AppResult<Json<OfflineStatus>> {
+    Ok(Json(app_state.offline_mode.set(request).await?))
+}
@@ -0,0 +1,104 @@
+use crate::{
+    handlers::history::RemoveQuery,
+    models::overrides::{AuditEntry, OverrideInfo, OverrideRequest, RemoveOverrideResponse},
+    system::{AdminAuth, AppState},
+    utils::errors::AppResult,
+};
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// 返回的最大条数，默认 100
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/overrides",
+    tag = "admin",
+    summary = "设置紧急覆盖",
+    description = "为配置文件设置管理员提供的内容，优先于缓存和上游返回。必须填写原因，可指定有效期，操作记录到审计日志",
+    request_body = OverrideRequest,
+    responses(
+        (status = 200, description = "设置成功", body = OverrideInfo),
+        (status = 400, description = "缺少原因或内容不是合法的 YAML", body = crate::utils::errors::ErrorResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn put_override(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+    Json(request): Json<OverrideRequest>,
+) -> AppResult<Json<OverrideInfo>> {
+    let path = app_state.proxy_service.canonical_path_for(&request.path)?;
+    Ok(Json(app_state.override_service.set(&path, request).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/overrides",
+    tag = "admin",
+    summary = "删除紧急覆盖",
+    description = "删除配置文件的紧急覆盖，恢复正常的缓存与上游刷新，操作记录到审计日志",
+    params(RemoveQuery),
+    responses(
+        (status = 200, description = "操作完成", body = RemoveOverrideResponse)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn delete_override(
+    _auth: AdminAuth,
+    Query(query): Query<RemoveQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<RemoveOverrideResponse>> {
+    let path = app_state.proxy_service.canonical_path_for(&query.path)?;
+    let removed = app_state
+        .override_service
+        .remove(&path, query.reason.as_deref())
+        .await?;
+    Ok(Json(RemoveOverrideResponse { path, removed }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/overrides",
+    tag = "admin",
+    summary = "列出紧急覆盖",
+    description = "列出所有生效中的紧急覆盖，不包含覆盖内容",
+    responses(
+        (status = 200, description = "成功返回覆盖列表", body = Vec<OverrideInfo>)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn list_overrides(
+    _auth: AdminAuth,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<Vec<OverrideInfo>>> {
+    Ok(Json(app_state.override_service.list().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    tag = "admin",
+    summary = "查看审计日志",
+    description = "按时间倒序返回最近的管理操作记录，包括紧急覆盖与版本固定",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "成功返回审计日志", body = Vec<AuditEntry>)
+    ),
+    security(("bearer" = []))
+)]
+pub async fn list_audit(
+    _auth: AdminAuth,
+    Query(query): Query<AuditQuery>,
+    State(app_state): State<AppState>,
+) -> AppResult<Json<Vec<AuditEntry>>> {
+    let limit = query.limit.unwrap_or(100);
+    Ok(Json(app_state.audit_service.list(limit).await?))
+}
@@ -1,10 +1,24 @@
+use crate::services::proxy_service::ProxyBody;
+use crate::utils::{
+    compression::{EncodedBody, accepts_encoding},
+    config_format::ConfigFormat,
+};
 use crate::{
     system::AppState,
     utils::errors::{AppError, AppResult},
 };
-use axum::{extract::State, http::Uri, response::Html};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, HeaderValue, Uri, header},
+    response::{Html, IntoResponse, Response},
+};
 use std::fs::read_to_string;
 
+const OVERLAY_HEADER: &str = "x-config-overlay";
+// 配置内容哈希，客户端可用于 POST /watch
+const HASH_HEADER: &str = "x-config-hash";
+
 pub async fn home_page(home_file_path: &str) -> AppResult<Html<String>> {
     let content = read_to_string(home_file_path)
         .map_err(|e| AppError::NotFound(format!("Home page not found: {}", e)))?;
@@ -16,7 +30,7 @@ pub async fn home_page(home_file_path: &str) -> AppResult<Html<String>> {
     path = "/{path}",
     tag = "proxy",
     summary = "代理配置中心请求",
-    description = "代理对配置中心的请求，支持缓存机制。只处理以.yml结尾的文件请求",
+    description = "代理对配置中心的请求，支持缓存机制。处理 yml/yaml/json/properties 文件请求，json 与 properties 由对应的 yml 转换得到，请求 yml 时也可通过 Accept 头协商输出格式。响应头 X-Config-Hash 为内容哈希，可直接用于 POST /watch",
     params(
         ("path" = String, description = "要代理的配置文件路径")
     ),
@@ -25,23 +39,100 @@ pub async fn home_page(home_file_path: &str) -> AppResult<Html<String>> {
         (status = 500, description = "代理请求失败", body = crate::utils::errors::ErrorResponse)
     )
 )]
-pub async fn proxy_config_center(uri: Uri, State(app_state): State<AppState>) -> AppResult<String> {
-    let uri_str = uri.to_string();
+pub async fn proxy_config_center(
+    uri: Uri,
+    headers: HeaderMap,
+    State(app_state): State<AppState>,
+) -> AppResult<Response> {
+    // 只使用路径与查询串，忽略 absolute-form 请求中携带的主机信息
+    let uri_str = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
 
     // 增加请求计数
     app_state.runtime_stats.increment_request_count();
 
-    match app_state.proxy_service.proxy_request(&uri_str).await? {
+    match app_state
+        .proxy_service
+        .proxy_request(uri_str, &headers)
+        .await?
+    {
         Some(result) => {
-            // 如果是从缓存返回的，增加缓存命中计数
+            // 如果是从缓存返回的，增加缓存命中计数；紧急覆盖单独计数
             if result.from_cache {
                 app_state.runtime_stats.increment_cache_hit_count();
             }
-            Ok(result.content)
+            if result.from_override {
+                app_state.runtime_stats.increment_override_hit_count();
+            }
+            let content_type = result.format.content_type();
+            let mut response = match result.body {
+                ProxyBody::Full(body) => negotiate_encoding(body, content_type, &headers)?,
+                ProxyBody::Stream(stream) => {
+                    content_response(Body::from_stream(stream), content_type)
+                }
+            };
+            // yml 请求的输出格式由 Accept 头协商；vary 请求头决定缓存键，
+            // 共享缓存不能把一个租户的配置返回给另一个租户
+            response
+                .headers_mut()
+                .append(header::VARY, HeaderValue::from_static("accept"));
+            for name in app_state.proxy_service.vary_header_names() {
+                response
+                    .headers_mut()
+                    .append(header::VARY, HeaderValue::from_name(name.clone()));
+            }
+            // 标明应用过的本地补丁来源
+            if !result.overlays.is_empty()
+                && let Ok(value) = HeaderValue::from_str(&result.overlays.join(", "))
+            {
+                response.headers_mut().insert(OVERLAY_HEADER, value);
+            }
+            if let Some(value) = result
+                .hash
+                .and_then(|hash| HeaderValue::from_str(&hash).ok())
+            {
+                response.headers_mut().insert(HASH_HEADER, value);
+            }
+            Ok(response)
         }
         None => {
-            // 非yml文件返回空字符串
-            Ok(String::new())
+            // 非配置文件返回空字符串
+            Ok(content_response(
+                Body::empty(),
+                ConfigFormat::Yaml.content_type(),
+            ))
         }
     }
 }
+
+/// 客户端接受缓存值的压缩编码时直接返回压缩内容，否则解压后返回
+fn negotiate_encoding(
+    body: EncodedBody,
+    content_type: &'static str,
+    headers: &HeaderMap,
+) -> AppResult<Response> {
+    let Some(encoding) = body.encoding.content_encoding() else {
+        return Ok(content_response(Body::from(body.data), content_type));
+    };
+
+    let accepted = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| accepts_encoding(value, encoding));
+    if !accepted {
+        return Ok(content_response(Body::from(body.decode()?), content_type));
+    }
+
+    let mut response = content_response(Body::from(body.data), content_type);
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    response_headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    Ok(response)
+}
+
+fn content_response(body: Body, content_type: &'static str) -> Response {
+    (
+        [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+        body,
+    )
+        .into_response()
+}
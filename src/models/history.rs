@@ -0,0 +1,122 @@
+use crate::models::overrides::OverrideInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ConfigVersion {
+    /// 内容哈希
+    pub hash: String,
+    /// 成为当前版本的时间戳（毫秒）
+    pub recorded_unix_time: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VersionListResponse {
+    /// 缓存键
+    pub key: String,
+    /// 版本列表，按时间倒序
+    pub versions: Vec<ConfigVersion>,
+}
+
+/// 固定记录，保存在 Redis 中，过期时间即固定时长
+#[derive(Serialize, Deserialize)]
+pub struct PinRecord {
+    /// 固定的版本哈希
+    pub hash: String,
+    /// 固定时间戳（毫秒）
+    pub pinned_unix_time: u64,
+    /// 固定原因
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PinInfo {
+    /// 缓存键
+    pub key: String,
+    /// 固定的版本哈希
+    pub hash: String,
+    /// 固定时间戳（毫秒）
+    pub pinned_unix_time: u64,
+    /// 剩余固定时间（秒）
+    pub expires_in_seconds: i64,
+    /// 固定原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PinRequest {
+    /// 配置文件请求路径
+    pub path: String,
+    /// 要固定的版本哈希
+    pub hash: String,
+    /// 固定时长（秒）
+    pub duration_secs: u64,
+    /// 固定原因
+    pub reason: Option<String>,
+    /// vary 请求头的取值，按请求头名称传入，用于定位缓存键
+    #[serde(default)]
+    pub vary: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UnpinResponse {
+    /// 缓存键
+    pub key: String,
+    /// 固定是否存在并已解除
+    pub removed: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CacheEntryInfo {
+    /// 缓存键
+    pub key: String,
+    /// 新鲜缓存剩余时间（秒），不存在时为空
+    pub ttl_seconds: Option<i64>,
+    /// 过期副本剩余时间（秒），不存在时为空
+    pub stale_ttl_seconds: Option<i64>,
+    /// 当前缓存内容的哈希
+    pub current_hash: Option<String>,
+    /// 生效中的版本固定
+    pub pin: Option<PinInfo>,
+    /// 生效中的紧急覆盖，优先于版本固定与缓存
+    pub active_override: Option<OverrideInfo>,
+    /// 已记录的版本数
+    pub version_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// 新增的键
+    Added,
+    /// 删除的键
+    Removed,
+    /// 取值变化的键
+    Changed,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PropertyChange {
+    /// 展平后的配置键，如 `spring.datasource.url`
+    pub key: String,
+    /// 变化类型
+    pub change: ChangeKind,
+    /// 旧值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<String>,
+    /// 新值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct StructuralDiff {
+    /// 旧版本哈希
+    pub from: String,
+    /// 新版本哈希
+    pub to: String,
+    /// 按配置键列出的变化
+    pub changes: Vec<PropertyChange>,
+}
@@ -1,2 +1,7 @@
+pub mod history;
+pub mod notifications;
+pub mod overrides;
+pub mod recording;
 pub mod responses;
-pub mod runtime; 
\ No newline at end of file
+pub mod runtime;
+pub mod snapshot;
\ No newline at end of file
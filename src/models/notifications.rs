@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSource {
+    /// 从上游刷新时发现内容变化
+    Refresh,
+    /// 配置仓库 Webhook 触发的变化
+    Webhook,
+    /// 管理员设置或删除紧急覆盖
+    Override,
+    /// 上游（Consul、etcd 等）监听到的变化
+    Upstream,
+    /// 管理员固定或解除固定历史版本
+    Pin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct DiffSummary {
+    /// 新增的配置项数量
+    pub added: usize,
+    /// 删除的配置项数量
+    pub removed: usize,
+    /// 取值变化的配置项数量
+    pub changed: usize,
+    /// 发生变化的配置键（不含取值，避免泄露敏感配置），最多列出前若干个
+    pub keys: Vec<String>,
+}
+
+/// 推送给外部 Webhook 的配置变更事件
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConfigChangedEvent {
+    /// 规范化后的 yml 路径
+    pub path: String,
+    /// 发生变化的缓存键
+    pub key: String,
+    /// 变化前的内容哈希
+    pub old_hash: String,
+    /// 变化后的内容哈希
+    pub new_hash: String,
+    /// 变化来源
+    pub source: ChangeSource,
+    /// 按展平配置键统计的差异摘要，旧版本不在历史中时为空
+    pub diff: Option<DiffSummary>,
+    /// 事件时间戳（毫秒）
+    pub unix_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct WatchItem {
+    /// 配置文件请求路径
+    pub path: String,
+    /// 客户端持有的版本哈希（代理响应头 X-Config-Hash），返回时为当前版本哈希；为空表示未知
+    pub hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct WatchRequest {
+    /// 要监听的配置文件及客户端持有的版本
+    pub items: Vec<WatchItem>,
+    /// 最长等待时间（秒），缺省使用服务端配置
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct WatchResponse {
+    /// 发生变化的配置文件，超时未变化时为空
+    pub changed: Vec<WatchItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ChangeEvent {
+    /// 规范化后的 yml 路径
+    pub path: String,
+    /// 新版本的内容哈希，为空表示需要重新拉取
+    pub hash: Option<String>,
+    /// 变化来源
+    pub source: ChangeSource,
+    /// 观察到变化的时间戳（毫秒）
+    pub unix_time: u64,
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 紧急覆盖记录，保存在 Redis 中
+#[derive(Serialize, Deserialize)]
+pub struct OverrideRecord {
+    /// 覆盖内容（YAML）
+    pub content: String,
+    /// 覆盖原因
+    pub reason: String,
+    /// 创建时间戳（毫秒）
+    pub created_unix_time: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OverrideRequest {
+    /// 配置文件请求路径，json/properties 路径会归并到对应的 yml
+    pub path: String,
+    /// 覆盖内容（YAML）
+    pub content: String,
+    /// 覆盖原因，必填
+    pub reason: String,
+    /// 有效期（秒），缺省时一直生效直到手动删除
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OverrideInfo {
+    /// 规范化后的 yml 路径
+    pub path: String,
+    /// 覆盖原因
+    pub reason: String,
+    /// 覆盖内容的哈希
+    pub hash: String,
+    /// 覆盖内容大小（字节）
+    pub size: usize,
+    /// 创建时间戳（毫秒）
+    pub created_unix_time: u64,
+    /// 剩余有效时间（秒），永久有效时为空
+    pub expires_in_seconds: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RemoveOverrideResponse {
+    /// 规范化后的 yml 路径
+    pub path: String,
+    /// 覆盖是否存在并已删除
+    pub removed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// 设置紧急覆盖
+    OverrideSet,
+    /// 删除紧急覆盖
+    OverrideRemoved,
+    /// 固定到历史版本
+    PinSet,
+    /// 解除版本固定
+    PinRemoved,
+    /// 进入离线模式
+    OfflineEnabled,
+    /// 退出离线模式
+    OfflineDisabled,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    /// 操作时间戳（毫秒）
+    pub unix_time: u64,
+    /// 操作类型
+    pub action: AuditAction,
+    /// 操作对象：路径或缓存键
+    pub target: String,
+    /// 操作原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 附加信息，如内容哈希或固定时长
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// 录制的一次上游响应，保存为录制目录中的一个 JSON 文件
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+    /// 规范化后的请求路径
+    pub path: String,
+    /// 上游响应状态码
+    pub status: u16,
+    /// 上游响应头
+    pub headers: Vec<(String, String)>,
+    /// UTF-8 响应体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// 非 UTF-8 响应体的 Base64 编码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+    /// 录制时间戳（毫秒）
+    pub recorded_unix_time: u64,
+}
@@ -9,4 +9,22 @@ pub struct ClearCacheResponse {
     pub message: String,
     /// 删除的缓存条目数量
     pub deleted_count: u64,
-} 
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct MonitorResponse {
+    /// 回调来源：github / gitlab / gitea
+    pub provider: String,
+    /// 推送事件类型，非 push 事件不做处理
+    pub event: String,
+    /// 推送的分支或标签
+    pub label: Option<String>,
+    /// 推送中变化的配置文件
+    pub changed_files: Vec<String>,
+    /// 推送负载中的文件列表不完整（提交数超出平台上限或缺少文件列表），已失效该分支的全部缓存
+    pub truncated: bool,
+    /// 已失效的缓存键
+    pub invalidated: Vec<String>,
+    /// 已从上游重新拉取的缓存键
+    pub refreshed: Vec<String>,
+}
\ No newline at end of file
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use utoipa::ToSchema;
@@ -9,15 +10,95 @@ pub struct RuntimeInfo {
     pub request_count: u64,
     /// 缓存命中数
     pub cache_hit_count: u64,
+    /// 返回紧急覆盖内容的请求数
+    pub override_hit_count: u64,
     /// 启动时间戳（毫秒）
     pub start_unix_time: u128,
+    /// 正在进行的上游请求数
+    pub upstream_in_flight: u64,
+    /// 等待上游并发名额的请求数
+    pub upstream_queue_depth: u64,
+    /// 因并发限制被拒绝的请求数
+    pub upstream_rejected_count: u64,
+    /// 因内容校验失败被拒绝缓存的上游响应数
+    pub validation_rejected_count: u64,
+    /// 默认上游（proxy_address）的熔断器状态
+    pub circuit_breaker: CircuitBreakerInfo,
+    /// 各上游的熔断器状态，按上游名称索引
+    pub upstream_circuit_breakers: BTreeMap<String, CircuitBreakerInfo>,
+    /// 是否处于离线（只读）模式
+    pub offline: bool,
+}
+
+/// 管理员设置的离线模式状态，保存在 Redis 中供所有副本共享
+#[derive(Serialize, Deserialize)]
+pub struct OfflineRecord {
+    /// 是否离线
+    pub enabled: bool,
+    /// 切换原因
+    pub reason: String,
+    /// 切换时间戳（毫秒）
+    pub updated_unix_time: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OfflineRequest {
+    /// 是否进入离线模式
+    pub enabled: bool,
+    /// 切换原因，必填
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OfflineStatus {
+    /// 当前是否处于离线模式
+    pub enabled: bool,
+    /// 配置文件中的默认值，管理员未切换过时生效
+    pub configured: bool,
+    /// 最近一次管理员切换的原因
+    pub reason: Option<String>,
+    /// 最近一次管理员切换的时间戳（毫秒）
+    pub updated_unix_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 关闭：正常放行
+    #[default]
+    Closed,
+    /// 打开：拒绝访问上游
+    Open,
+    /// 半开：放行少量试探请求
+    HalfOpen,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct CircuitBreakerInfo {
+    /// 是否启用
+    pub enabled: bool,
+    /// 当前状态
+    pub state: CircuitState,
+    /// 滑动窗口内的失败率
+    pub failure_rate: f64,
+    /// 滑动窗口内的请求数
+    pub window_calls: u64,
+    /// 状态切换次数
+    pub transition_count: u64,
+    /// 最近一次状态切换时间戳（毫秒）
+    pub last_transition_unix_time: u128,
 }
 
 #[derive(Debug)]
 pub struct RuntimeStats {
     pub request_count: AtomicU64,
     pub cache_hit_count: AtomicU64,
+    pub override_hit_count: AtomicU64,
     pub start_unix_time: u128,
+    pub upstream_in_flight: AtomicU64,
+    pub upstream_queue_depth: AtomicU64,
+    pub upstream_rejected_count: AtomicU64,
+    pub validation_rejected_count: AtomicU64,
 }
 
 impl RuntimeStats {
@@ -25,10 +106,15 @@ impl RuntimeStats {
         Self {
             request_count: AtomicU64::new(0),
             cache_hit_count: AtomicU64::new(0),
+            override_hit_count: AtomicU64::new(0),
             start_unix_time: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis(),
+            upstream_in_flight: AtomicU64::new(0),
+            upstream_queue_depth: AtomicU64::new(0),
+            upstream_rejected_count: AtomicU64::new(0),
+            validation_rejected_count: AtomicU64::new(0),
         }
     }
 
@@ -36,7 +122,15 @@ impl RuntimeStats {
         RuntimeInfo {
             request_count: self.request_count.load(Ordering::Relaxed),
             cache_hit_count: self.cache_hit_count.load(Ordering::Relaxed),
+            override_hit_count: self.override_hit_count.load(Ordering::Relaxed),
             start_unix_time: self.start_unix_time,
+            upstream_in_flight: self.upstream_in_flight.load(Ordering::Relaxed),
+            upstream_queue_depth: self.upstream_queue_depth.load(Ordering::Relaxed),
+            upstream_rejected_count: self.upstream_rejected_count.load(Ordering::Relaxed),
+            validation_rejected_count: self.validation_rejected_count.load(Ordering::Relaxed),
+            circuit_breaker: CircuitBreakerInfo::default(),
+            upstream_circuit_breakers: BTreeMap::new(),
+            offline: false,
         }
     }
 
@@ -47,4 +141,38 @@ impl RuntimeStats {
     pub fn increment_cache_hit_count(&self) {
         self.cache_hit_count.fetch_add(1, Ordering::Relaxed);
     }
-} 
\ No newline at end of file
+
+    pub fn increment_override_hit_count(&self) {
+        self.override_hit_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_upstream_in_flight(&self) {
+        self.upstream_in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn decrement_upstream_in_flight(&self) {
+        self.upstream_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// 队列深度小于上限时加一，检查与递增为同一次原子操作
+    pub fn try_increment_upstream_queue_depth(&self, max: u64) -> bool {
+        self.upstream_queue_depth
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| {
+                (depth < max).then_some(depth + 1)
+            })
+            .is_ok()
+    }
+
+    pub fn decrement_upstream_queue_depth(&self) {
+        self.upstream_queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_upstream_rejected_count(&self) {
+        self.upstream_rejected_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_validation_rejected_count(&self) {
+        self.validation_rejected_count
+            .fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// 磁盘快照索引，保存在快照目录的 index.json 中
+#[derive(Serialize, Deserialize, Default)]
+pub struct SnapshotIndex {
+    /// 快照写入时间戳（毫秒）
+    pub created_unix_time: u64,
+    /// 快照中的缓存条目
+    pub entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SnapshotEntry {
+    /// 缓存键
+    pub key: String,
+    /// 内容哈希，同时是 entries 目录下的内容文件名
+    pub hash: String,
+    /// 内容大小（字节）
+    pub size: usize,
+    /// 最近一次在 Redis 中看到该条目的时间戳（毫秒）
+    pub captured_unix_time: u64,
+}
@@ -1 +1,2 @@
-pub mod redis_repository; 
\ No newline at end of file
+pub mod redis_repository;
+pub mod snapshot_repository;
\ No newline at end of file
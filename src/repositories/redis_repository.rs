@@ -1,18 +1,72 @@
+use crate::system::CompressionConfig;
+use crate::utils::compression::EncodedBody;
 use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
 use deadpool_redis::{Config, Connection, Pool, Runtime, redis::cmd};
 use redis::AsyncCommands;
 use std::sync::Arc;
-use urlencoding::encode;
+use urlencoding::{decode, encode};
 
 const CACHE_PREFIX: &str = "config_cache:";
+const STALE_PREFIX: &str = "config_stale:";
+// 版本历史：有序集合按记录时间保存内容哈希，版本内容单独存放
+const HISTORY_PREFIX: &str = "config_history:";
+const VERSION_PREFIX: &str = "config_version:";
+// 版本固定记录，过期时间即固定时长
+const PIN_PREFIX: &str = "config_pin:";
+// 紧急覆盖内容，按规范化路径存放
+const OVERRIDE_PREFIX: &str = "config_override:";
+// 管理操作审计日志，新条目在列表头部
+const AUDIT_LOG_KEY: &str = "config_audit";
+// 管理员切换的离线模式状态
+const OFFLINE_KEY: &str = "config_offline";
+// 每个缓存键当前内容的哈希，用于发现内容变化，与过期副本一同过期
+const CURRENT_HASH_PREFIX: &str = "config_current:";
+
+// 哈希变化时原子地更新当前哈希，存在之前的哈希时发布事件（频道为空时不发布），
+// 返回是否发生了变化以及之前的哈希（不存在时为空串）。未变化时只续期
+const COMPARE_AND_PUBLISH: &str = r"
+local previous = redis.call('GET', KEYS[1])
+if previous == ARGV[1] then
+    redis.call('EXPIRE', KEYS[1], ARGV[2])
+    return {0, ''}
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+if previous and ARGV[3] ~= '' then
+    redis.call('PUBLISH', ARGV[3], ARGV[4])
+end
+return {1, previous or ''}
+";
+
+// 删除存在的覆盖并写入审计日志，覆盖不存在时不写入，返回删除的键数
+const DELETE_AUDITED: &str = r"
+local deleted = redis.call('DEL', KEYS[1])
+if deleted > 0 then
+    redis.call('LPUSH', KEYS[2], ARGV[1])
+    redis.call('LTRIM', KEYS[2], 0, tonumber(ARGV[2]) - 1)
+end
+return deleted
+";
+
+// 只延长不缩短过期时间：剩余时间短于给定秒数时续期，没有过期时间（离线模式持久化）或不存在的键不变
+const EXTEND_EXPIRE: &str = r"
+for _, key in ipairs(KEYS) do
+    local ttl = redis.call('TTL', key)
+    if ttl >= 0 and ttl < tonumber(ARGV[1]) then
+        redis.call('EXPIRE', key, ARGV[1])
+    end
+end
+return 0
+";
 
 #[derive(Clone)]
 pub struct RedisRepository {
     pool: Arc<Pool>,
+    compression: CompressionConfig,
 }
 
 impl RedisRepository {
-    pub fn new(redis_url: &str) -> AppResult<Self> {
+    pub fn new(redis_url: &str, compression: CompressionConfig) -> AppResult<Self> {
         let cfg = Config::from_url(redis_url);
         let pool = cfg
             .create_pool(Some(Runtime::Tokio1))
@@ -20,6 +74,7 @@ impl RedisRepository {
         
         Ok(Self {
             pool: Arc::new(pool),
+            compression,
         })
     }
 
@@ -29,32 +84,637 @@ impl RedisRepository {
         Ok(())
     }
 
-    pub async fn get(&self, key: &str) -> AppResult<Option<String>> {
+    /// 在同一次往返中读取缓存与固定记录，未固定的键不需要额外的请求
+    pub async fn get_with_pin(
+        &self,
+        key: &str,
+    ) -> AppResult<(Option<EncodedBody>, Option<String>)> {
         let mut conn = self.get_connection().await?;
         let cache_key = format!("{}{}", CACHE_PREFIX, encode(key));
-        
-        let result: Option<String> = conn
+        let pin_key = format!("{}{}", PIN_PREFIX, encode(key));
+
+        let (cached, pin): (Option<Vec<u8>>, Option<String>) = redis::pipe()
             .get(&cache_key)
+            .get(&pin_key)
+            .query_async(&mut conn)
             .await
             .map_err(AppError::RedisCommand)?;
-        
-        Ok(result)
+
+        Ok((
+            cached.map(|stored| EncodedBody::from_stored(Bytes::from(stored))),
+            pin,
+        ))
     }
 
-    pub async fn set(&self, key: &str, value: &str, expire_seconds: u64) -> AppResult<()> {
+    pub async fn get(&self, key: &str) -> AppResult<Option<EncodedBody>> {
         let mut conn = self.get_connection().await?;
         let cache_key = format!("{}{}", CACHE_PREFIX, encode(key));
         
-        let _: () = conn.set_ex(&cache_key, value, expire_seconds)
+        let result: Option<Vec<u8>> = conn
+            .get(&cache_key)
             .await
             .map_err(AppError::RedisCommand)?;
         
+        Ok(result.map(|stored| EncodedBody::from_stored(Bytes::from(stored))))
+    }
+
+    /// 读取过期副本，新鲜缓存过期后仍可在上游不可用时兜底
+    pub async fn get_stale(&self, key: &str) -> AppResult<Option<EncodedBody>> {
+        let mut conn = self.get_connection().await?;
+        let stale_key = format!("{}{}", STALE_PREFIX, encode(key));
+
+        let result: Option<Vec<u8>> = conn
+            .get(&stale_key)
+            .await
+            .map_err(AppError::RedisCommand)?;
+
+        Ok(result.map(|stored| EncodedBody::from_stored(Bytes::from(stored))))
+    }
+
+    /// 写入缓存，同时写入一份过期时间更长的过期副本；内容哈希与值一起保存
+    pub async fn set(
+        &self,
+        key: &str,
+        value: &[u8],
+        hash: &str,
+        expire_seconds: u64,
+        stale_expire_seconds: u64,
+    ) -> AppResult<()> {
+        let stored = self.encode_value(value, hash);
+        let mut conn = self.get_connection().await?;
+        let cache_key = format!("{}{}", CACHE_PREFIX, encode(key));
+        let stale_key = format!("{}{}", STALE_PREFIX, encode(key));
+
+        let _: () = redis::pipe()
+            .set_ex(&cache_key, &stored, expire_seconds)
+            .ignore()
+            .set_ex(&stale_key, &stored, stale_expire_seconds.max(expire_seconds))
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+
         Ok(())
     }
 
+    /// 只写入过期副本，用于从磁盘快照恢复：上游可用时仍会重新拉取
+    pub async fn set_stale(
+        &self,
+        key: &str,
+        value: &[u8],
+        hash: &str,
+        expire_seconds: u64,
+    ) -> AppResult<()> {
+        let stored = self.encode_value(value, hash);
+        let mut conn = self.get_connection().await?;
+        let stale_key = format!("{}{}", STALE_PREFIX, encode(key));
+
+        let _: () = conn
+            .set_ex(&stale_key, &stored, expire_seconds)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 记录一个版本，与最新版本内容相同时只刷新过期时间；超出保留数量的旧版本连同内容一起删除。
+    /// 被固定的版本不会被删除，其内容至少保留到固定结束（`pinned` 为固定的哈希与剩余秒数）。
+    /// 返回是否写入了新版本
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_version(
+        &self,
+        key: &str,
+        hash: &str,
+        value: &[u8],
+        timestamp_ms: u64,
+        max_versions: usize,
+        expire_seconds: u64,
+        pinned: Option<(&str, u64)>,
+    ) -> AppResult<bool> {
+        let mut conn = self.get_connection().await?;
+        let history_key = format!("{}{}", HISTORY_PREFIX, encode(key));
+
+        let latest: Vec<String> = conn
+            .zrevrange(&history_key, 0, 0)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        if latest.first().is_some_and(|latest| latest == hash) {
+            let versions: Vec<String> = conn
+                .zrange(&history_key, 0, -1)
+                .await
+                .map_err(AppError::RedisCommand)?;
+            let _: () = conn
+                .expire(&history_key, expire_seconds as i64)
+                .await
+                .map_err(AppError::RedisCommand)?;
+            let version_keys: Vec<String> = versions
+                .iter()
+                .map(|version| Self::version_key(key, version))
+                .collect();
+            Self::extend_expire(&mut conn, &version_keys, expire_seconds).await?;
+            self.extend_pinned(&mut conn, key, pinned).await?;
+            return Ok(false);
+        }
+
+        let stored = self.encode_value(value, hash);
+        let _: () = redis::pipe()
+            .set_ex(Self::version_key(key, hash), &stored, expire_seconds)
+            .ignore()
+            .zadd(&history_key, hash, timestamp_ms)
+            .ignore()
+            .expire(&history_key, expire_seconds as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        // 新内容与固定版本相同时，上面的写入会缩短固定版本的过期时间
+        self.extend_pinned(&mut conn, key, pinned).await?;
+
+        let expired: Vec<String> = conn
+            .zrange(&history_key, 0, -(max_versions.max(1) as isize) - 1)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        let expired: Vec<String> = expired
+            .into_iter()
+            .filter(|version| pinned.is_none_or(|(pinned, _)| pinned != version))
+            .collect();
+        if !expired.is_empty() {
+            let version_keys: Vec<String> = expired
+                .iter()
+                .map(|hash| Self::version_key(key, hash))
+                .collect();
+            let _: () = redis::pipe()
+                .del(&version_keys)
+                .ignore()
+                .zrem(&history_key, &expired)
+                .ignore()
+                .query_async(&mut conn)
+                .await
+                .map_err(AppError::RedisCommand)?;
+        }
+
+        Ok(true)
+    }
+
+    async fn extend_pinned(
+        &self,
+        conn: &mut Connection,
+        key: &str,
+        pinned: Option<(&str, u64)>,
+    ) -> AppResult<()> {
+        let Some((hash, remaining)) = pinned else {
+            return Ok(());
+        };
+        Self::extend_expire(conn, &[Self::version_key(key, hash)], remaining).await
+    }
+
+    async fn extend_expire(
+        conn: &mut Connection,
+        keys: &[String],
+        expire_seconds: u64,
+    ) -> AppResult<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let script = redis::Script::new(EXTEND_EXPIRE);
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(key);
+        }
+        let _: i64 = invocation
+            .arg(expire_seconds.max(1))
+            .invoke_async(conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 按时间倒序列出版本哈希与记录时间（毫秒）
+    pub async fn list_versions(&self, key: &str) -> AppResult<Vec<(String, u64)>> {
+        let mut conn = self.get_connection().await?;
+        let history_key = format!("{}{}", HISTORY_PREFIX, encode(key));
+
+        let versions: Vec<(String, f64)> = conn
+            .zrevrange_withscores(&history_key, 0, -1)
+            .await
+            .map_err(AppError::RedisCommand)?;
+
+        Ok(versions
+            .into_iter()
+            .map(|(hash, score)| (hash, score as u64))
+            .collect())
+    }
+
+    pub async fn has_version(&self, key: &str, hash: &str) -> AppResult<bool> {
+        let mut conn = self.get_connection().await?;
+
+        conn.exists(Self::version_key(key, hash))
+            .await
+            .map_err(AppError::RedisCommand)
+    }
+
+    pub async fn get_version(&self, key: &str, hash: &str) -> AppResult<Option<EncodedBody>> {
+        let mut conn = self.get_connection().await?;
+
+        let result: Option<Vec<u8>> = conn
+            .get(Self::version_key(key, hash))
+            .await
+            .map_err(AppError::RedisCommand)?;
+
+        Ok(result.map(|stored| EncodedBody::from_stored(Bytes::from(stored))))
+    }
+
+    /// 写入固定记录，并把被固定版本的内容至少保留到固定结束
+    pub async fn set_pin(
+        &self,
+        key: &str,
+        hash: &str,
+        record: &str,
+        expire_seconds: u64,
+    ) -> AppResult<()> {
+        let mut conn = self.get_connection().await?;
+        let pin_key = format!("{}{}", PIN_PREFIX, encode(key));
+
+        let _: () = conn
+            .set_ex(&pin_key, record, expire_seconds)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Self::extend_expire(&mut conn, &[Self::version_key(key, hash)], expire_seconds).await
+    }
+
+    /// 读取固定记录及其剩余时间（秒）
+    pub async fn get_pin(&self, key: &str) -> AppResult<Option<(String, i64)>> {
+        let mut conn = self.get_connection().await?;
+        let pin_key = format!("{}{}", PIN_PREFIX, encode(key));
+
+        let (record, ttl): (Option<String>, i64) = redis::pipe()
+            .get(&pin_key)
+            .ttl(&pin_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+
+        Ok(record.map(|record| (record, ttl)))
+    }
+
+    pub async fn delete_pin(&self, key: &str) -> AppResult<bool> {
+        let mut conn = self.get_connection().await?;
+        let pin_key = format!("{}{}", PIN_PREFIX, encode(key));
+
+        let deleted: usize = conn.del(&pin_key).await.map_err(AppError::RedisCommand)?;
+        Ok(deleted > 0)
+    }
+
+    /// 列出全部固定记录：缓存键、记录内容与剩余时间（秒）
+    pub async fn list_pins(&self) -> AppResult<Vec<(String, String, i64)>> {
+        self.scan_records(PIN_PREFIX).await
+    }
+
+    /// 写入紧急覆盖并在同一事务中写入审计日志条目，未指定过期时间时永久有效
+    pub async fn set_override(
+        &self,
+        path: &str,
+        record: &str,
+        expire_seconds: Option<u64>,
+        audit_entry: &str,
+        max_audit_entries: usize,
+    ) -> AppResult<()> {
+        let mut conn = self.get_connection().await?;
+        let override_key = format!("{}{}", OVERRIDE_PREFIX, encode(path));
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        match expire_seconds {
+            Some(seconds) => pipe.set_ex(&override_key, record, seconds),
+            None => pipe.set(&override_key, record),
+        }
+        .ignore();
+        let _: () = pipe
+            .lpush(AUDIT_LOG_KEY, audit_entry)
+            .ignore()
+            .ltrim(AUDIT_LOG_KEY, 0, max_audit_entries.max(1) as isize - 1)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 读取紧急覆盖记录及其剩余时间（秒），永久有效时为 -1
+    pub async fn get_override(&self, path: &str) -> AppResult<Option<(String, i64)>> {
+        let mut conn = self.get_connection().await?;
+        let override_key = format!("{}{}", OVERRIDE_PREFIX, encode(path));
+
+        let (record, ttl): (Option<String>, i64) = redis::pipe()
+            .get(&override_key)
+            .ttl(&override_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+
+        Ok(record.map(|record| (record, ttl)))
+    }
+
+    /// 删除覆盖内容，覆盖存在时原子地写入审计日志条目
+    pub async fn delete_override(
+        &self,
+        path: &str,
+        audit_entry: &str,
+        max_audit_entries: usize,
+    ) -> AppResult<bool> {
+        let mut conn = self.get_connection().await?;
+        let override_key = format!("{}{}", OVERRIDE_PREFIX, encode(path));
+
+        let deleted: usize = redis::Script::new(DELETE_AUDITED)
+            .key(&override_key)
+            .key(AUDIT_LOG_KEY)
+            .arg(audit_entry)
+            .arg(max_audit_entries.max(1))
+            .invoke_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(deleted > 0)
+    }
+
+    /// 列出全部紧急覆盖：路径、记录内容与剩余时间（秒）
+    pub async fn list_overrides(&self) -> AppResult<Vec<(String, String, i64)>> {
+        self.scan_records(OVERRIDE_PREFIX).await
+    }
+
+    /// 追加一条审计日志，只保留最近 max_entries 条
+    pub async fn append_audit(&self, entry: &str, max_entries: usize) -> AppResult<()> {
+        let mut conn = self.get_connection().await?;
+
+        let _: () = redis::pipe()
+            .lpush(AUDIT_LOG_KEY, entry)
+            .ignore()
+            .ltrim(AUDIT_LOG_KEY, 0, max_entries.max(1) as isize - 1)
+            .ignore()
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 读取最近的审计日志，按时间倒序
+    pub async fn list_audit(&self, limit: usize) -> AppResult<Vec<String>> {
+        let mut conn = self.get_connection().await?;
+
+        conn.lrange(AUDIT_LOG_KEY, 0, limit.max(1) as isize - 1)
+            .await
+            .map_err(AppError::RedisCommand)
+    }
+
+    /// 缓存键内容哈希变化时更新并向频道发布消息，多个副本同时观察到同一变化时只有一个会发布。
+    /// 发生变化时返回之前的哈希，首次记录时为 `Some(None)` 且不发布
+    pub async fn compare_and_publish(
+        &self,
+        key: &str,
+        hash: &str,
+        expire_seconds: u64,
+        channel: &str,
+        message: &str,
+    ) -> AppResult<Option<Option<String>>> {
+        let mut conn = self.get_connection().await?;
+
+        let (changed, previous): (i32, String) = redis::Script::new(COMPARE_AND_PUBLISH)
+            .key(format!("{}{}", CURRENT_HASH_PREFIX, encode(key)))
+            .arg(hash)
+            .arg(expire_seconds.max(1))
+            .arg(channel)
+            .arg(message)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok((changed == 1).then(|| (!previous.is_empty()).then_some(previous)))
+    }
+
+    pub async fn publish(&self, channel: &str, message: &str) -> AppResult<()> {
+        let mut conn = self.get_connection().await?;
+
+        let _: i64 = conn
+            .publish(channel, message)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 缓存键当前内容的哈希
+    pub async fn current_hash(&self, key: &str) -> AppResult<Option<String>> {
+        let mut conn = self.get_connection().await?;
+
+        conn.get(format!("{}{}", CURRENT_HASH_PREFIX, encode(key)))
+            .await
+            .map_err(AppError::RedisCommand)
+    }
+
+    /// 扫描指定前缀下的记录，返回解码后的键、记录内容与剩余时间（秒）
+    async fn scan_records(&self, prefix: &str) -> AppResult<Vec<(String, String, i64)>> {
+        let mut conn = self.get_connection().await?;
+        let mut cursor = 0u64;
+        let mut records = Vec::new();
+
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", prefix))
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut conn)
+                .await
+                .map_err(AppError::RedisCommand)?;
+
+            for redis_key in keys {
+                let (record, ttl): (Option<String>, i64) = redis::pipe()
+                    .get(&redis_key)
+                    .ttl(&redis_key)
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(AppError::RedisCommand)?;
+                let encoded = redis_key.trim_start_matches(prefix);
+                if let (Some(record), Ok(key)) = (record, decode(encoded)) {
+                    records.push((key.into_owned(), record, ttl));
+                }
+            }
+
+            cursor = next_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// 列出所有新鲜缓存的缓存键
+    pub async fn list_cache_keys(&self) -> AppResult<Vec<String>> {
+        self.list_keys(CACHE_PREFIX).await
+    }
+
+    /// 列出所有过期副本的缓存键
+    pub async fn list_stale_keys(&self) -> AppResult<Vec<String>> {
+        self.list_keys(STALE_PREFIX).await
+    }
+
+    async fn list_keys(&self, prefix: &str) -> AppResult<Vec<String>> {
+        let mut conn = self.get_connection().await?;
+        let mut cursor = 0u64;
+        let mut cache_keys = Vec::new();
+
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", prefix))
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut conn)
+                .await
+                .map_err(AppError::RedisCommand)?;
+
+            cache_keys.extend(keys.iter().filter_map(|redis_key| {
+                decode(redis_key.trim_start_matches(prefix))
+                    .ok()
+                    .map(|key| key.into_owned())
+            }));
+
+            cursor = next_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        Ok(cache_keys)
+    }
+
+    /// 删除新鲜缓存，保留过期副本作为上游不可用时的兜底
+    pub async fn invalidate(&self, keys: &[String]) -> AppResult<usize> {
+        let mut conn = self.get_connection().await?;
+        let redis_keys: Vec<String> = keys
+            .iter()
+            .map(|key| format!("{}{}", CACHE_PREFIX, encode(key)))
+            .collect();
+        self.delete_keys(&mut conn, &redis_keys).await
+    }
+
+    pub async fn get_offline(&self) -> AppResult<Option<String>> {
+        let mut conn = self.get_connection().await?;
+
+        conn.get(OFFLINE_KEY).await.map_err(AppError::RedisCommand)
+    }
+
+    pub async fn set_offline(&self, record: &str) -> AppResult<()> {
+        let mut conn = self.get_connection().await?;
+
+        let _: () = conn
+            .set(OFFLINE_KEY, record)
+            .await
+            .map_err(AppError::RedisCommand)?;
+        Ok(())
+    }
+
+    /// 移除所有缓存、过期副本与当前哈希的过期时间，返回处理的键数量
+    pub async fn persist_cache(&self) -> AppResult<usize> {
+        let mut total = 0;
+        for prefix in [CACHE_PREFIX, STALE_PREFIX, CURRENT_HASH_PREFIX] {
+            total += self.update_ttl(prefix, None).await?;
+        }
+        Ok(total)
+    }
+
+    /// 为所有缓存、过期副本与当前哈希重新设置过期时间，返回处理的键数量
+    pub async fn restore_cache_ttl(
+        &self,
+        expire_seconds: u64,
+        stale_expire_seconds: u64,
+    ) -> AppResult<usize> {
+        let stale_expire_seconds = stale_expire_seconds.max(expire_seconds);
+        Ok(self.update_ttl(CACHE_PREFIX, Some(expire_seconds)).await?
+            + self
+                .update_ttl(STALE_PREFIX, Some(stale_expire_seconds))
+                .await?
+            + self
+                .update_ttl(CURRENT_HASH_PREFIX, Some(stale_expire_seconds))
+                .await?)
+    }
+
+    /// 按前缀批量设置过期时间，`None` 表示永不过期
+    async fn update_ttl(&self, prefix: &str, expire_seconds: Option<u64>) -> AppResult<usize> {
+        let mut conn = self.get_connection().await?;
+        let mut cursor = 0u64;
+        let mut total = 0usize;
+
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", prefix))
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut conn)
+                .await
+                .map_err(AppError::RedisCommand)?;
+
+            if !keys.is_empty() {
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    match expire_seconds {
+                        Some(seconds) => pipe.expire(key, seconds as i64).ignore(),
+                        None => pipe.persist(key).ignore(),
+                    };
+                }
+                let _: () = pipe
+                    .query_async(&mut conn)
+                    .await
+                    .map_err(AppError::RedisCommand)?;
+                total += keys.len();
+            }
+
+            cursor = next_cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// 新鲜缓存与过期副本的剩余时间（秒），键不存在时为 -2
+    pub async fn cache_ttls(&self, key: &str) -> AppResult<(i64, i64)> {
+        let mut conn = self.get_connection().await?;
+        let cache_key = format!("{}{}", CACHE_PREFIX, encode(key));
+        let stale_key = format!("{}{}", STALE_PREFIX, encode(key));
+
+        redis::pipe()
+            .ttl(&cache_key)
+            .ttl(&stale_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(AppError::RedisCommand)
+    }
+
+    fn version_key(key: &str, hash: &str) -> String {
+        format!("{}{}:{}", VERSION_PREFIX, encode(key), hash)
+    }
+
+    /// 删除所有缓存、过期副本、当前哈希、版本历史与指向历史版本的固定
     pub async fn delete_all(&self) -> AppResult<usize> {
+        let mut total_deleted = 0usize;
+        for prefix in [
+            CACHE_PREFIX,
+            STALE_PREFIX,
+            CURRENT_HASH_PREFIX,
+            HISTORY_PREFIX,
+            VERSION_PREFIX,
+            PIN_PREFIX,
+        ] {
+            total_deleted += self.delete_by_pattern(&format!("{}*", prefix)).await?;
+        }
+        Ok(total_deleted)
+    }
+
+    async fn delete_by_pattern(&self, pattern: &str) -> AppResult<usize> {
         let mut conn = self.get_connection().await?;
-        let pattern = format!("{}*", CACHE_PREFIX);
         let mut cursor = 0u64;
         let mut total_deleted = 0usize;
 
@@ -62,7 +722,7 @@ impl RedisRepository {
             let (next_cursor, keys): (u64, Vec<String>) = cmd("SCAN")
                 .arg(cursor)
                 .arg("MATCH")
-                .arg(&pattern)
+                .arg(pattern)
                 .arg("COUNT")
                 .arg(100)
                 .query_async(&mut conn)
@@ -83,6 +743,31 @@ impl RedisRepository {
         Ok(total_deleted)
     }
 
+    /// 按配置压缩并加上格式头字节，压缩失败时退回未压缩存储
+    fn encode_value(&self, value: &[u8], hash: &str) -> Vec<u8> {
+        let algorithm = self.compression.algorithm;
+        let identity = || EncodedBody {
+            hash: Some(hash.to_string()),
+            ..EncodedBody::identity(Bytes::copy_from_slice(value))
+        };
+        if value.len() < self.compression.min_size {
+            return identity().to_stored();
+        }
+
+        match algorithm.compress(value, self.compression.level) {
+            Ok(compressed) => EncodedBody {
+                encoding: algorithm,
+                data: Bytes::from(compressed),
+                hash: Some(hash.to_string()),
+            }
+            .to_stored(),
+            Err(e) => {
+                tracing::warn!("Failed to compress cache value: {}", e);
+                identity().to_stored()
+            }
+        }
+    }
+
     async fn get_connection(&self) -> AppResult<Connection> {
         self.pool.get().await.map_err(AppError::Redis)
     }
@@ -0,0 +1,186 @@
+use crate::models::snapshot::{SnapshotEntry, SnapshotIndex};
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+const INDEX_FILE: &str = "index.json";
+const ENTRIES_DIR: &str = "entries";
+const ENTRY_EXT: &str = "yml";
+
+/// 本地磁盘快照：index.json 记录缓存键到内容哈希的映射，
+/// 内容按哈希存放在 entries 目录，相同内容只保存一份
+pub struct SnapshotRepository {
+    directory: PathBuf,
+    entries: RwLock<HashMap<String, SnapshotEntry>>,
+}
+
+impl SnapshotRepository {
+    /// 打开快照目录并加载已有索引，索引损坏时视为空快照
+    pub fn open(directory: &str) -> AppResult<Self> {
+        let directory = PathBuf::from(directory);
+        std::fs::create_dir_all(directory.join(ENTRIES_DIR)).map_err(|e| {
+            AppError::Config(format!(
+                "Failed to create snapshot directory '{}': {}",
+                directory.display(),
+                e
+            ))
+        })?;
+
+        let index = match std::fs::read(directory.join(INDEX_FILE)) {
+            Ok(content) => serde_json::from_slice::<SnapshotIndex>(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring corrupted snapshot index: {}", e);
+                SnapshotIndex::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => SnapshotIndex::default(),
+            Err(e) => {
+                return Err(AppError::Config(format!(
+                    "Failed to read snapshot index in '{}': {}",
+                    directory.display(),
+                    e
+                )));
+            }
+        };
+
+        let entries = index
+            .entries
+            .into_iter()
+            .map(|entry| (entry.key.clone(), entry))
+            .collect();
+        Ok(Self {
+            directory,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    pub fn entries(&self) -> Vec<SnapshotEntry> {
+        self.read_entries().values().cloned().collect()
+    }
+
+    pub async fn get(&self, key: &str) -> AppResult<Option<Bytes>> {
+        let Some(hash) = self.read_entries().get(key).map(|entry| entry.hash.clone()) else {
+            return Ok(None);
+        };
+
+        match fs::read(self.entry_path(&hash)).await {
+            Ok(content) => Ok(Some(Bytes::from(content))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(AppError::Cache(format!(
+                "Failed to read snapshot entry {}: {}",
+                hash, e
+            ))),
+        }
+    }
+
+    /// 从索引中移除匹配的条目，内容文件在下次写入快照时删除
+    pub fn discard(&self, predicate: impl Fn(&str) -> bool) {
+        self.entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .retain(|key, _| !predicate(key));
+    }
+
+    /// 写入新快照。本次未出现的旧条目在保留期内继续保留，
+    /// 避免 Redis 丢失数据后写出的空快照覆盖仍可用的内容。返回快照中的条目数
+    pub async fn save(
+        &self,
+        captured: Vec<(String, String, Bytes)>,
+        retention_secs: u64,
+    ) -> AppResult<usize> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let cutoff = now.saturating_sub(retention_secs.saturating_mul(1000));
+
+        let mut entries: HashMap<String, SnapshotEntry> = self
+            .read_entries()
+            .iter()
+            .filter(|(_, entry)| entry.captured_unix_time >= cutoff)
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect();
+
+        for (key, hash, content) in captured {
+            let path = self.entry_path(&hash);
+            if fs::metadata(&path).await.is_err() {
+                write_atomic(&path, &content).await?;
+            }
+            entries.insert(
+                key.clone(),
+                SnapshotEntry {
+                    key,
+                    hash,
+                    size: content.len(),
+                    captured_unix_time: now,
+                },
+            );
+        }
+
+        let mut index = SnapshotIndex {
+            created_unix_time: now,
+            entries: entries.values().cloned().collect(),
+        };
+        index.entries.sort_by(|a, b| a.key.cmp(&b.key));
+        write_atomic(
+            &self.directory.join(INDEX_FILE),
+            &serde_json::to_vec_pretty(&index)?,
+        )
+        .await?;
+
+        let referenced: HashSet<String> = entries.values().map(|e| e.hash.clone()).collect();
+        let count = entries.len();
+        *self
+            .entries
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = entries;
+        self.remove_unreferenced(&referenced).await;
+        Ok(count)
+    }
+
+    /// 删除索引不再引用的内容文件，失败只记录日志
+    async fn remove_unreferenced(&self, referenced: &HashSet<String>) {
+        let mut dir = match fs::read_dir(self.directory.join(ENTRIES_DIR)).await {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("Failed to list snapshot entries: {}", e);
+                return;
+            }
+        };
+        while let Ok(Some(file)) = dir.next_entry().await {
+            let path = file.path();
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            if stem.is_some_and(|stem| !referenced.contains(stem))
+                && let Err(e) = fs::remove_file(&path).await
+            {
+                tracing::warn!("Failed to remove snapshot entry {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn entry_path(&self, hash: &str) -> PathBuf {
+        self.directory
+            .join(ENTRIES_DIR)
+            .join(format!("{}.{}", hash, ENTRY_EXT))
+    }
+
+    fn read_entries(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, SnapshotEntry>> {
+        self.entries
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 先写临时文件再重命名，进程中途退出也不会留下半个文件
+async fn write_atomic(path: &Path, content: &[u8]) -> AppResult<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)
+        .await
+        .map_err(|e| AppError::Cache(format!("Failed to write {}: {}", tmp.display(), e)))?;
+    fs::rename(&tmp, path)
+        .await
+        .map_err(|e| AppError::Cache(format!("Failed to write {}: {}", path.display(), e)))
+}
@@ -0,0 +1,75 @@
+use crate::models::overrides::{AuditAction, AuditEntry};
+use crate::repositories::redis_repository::RedisRepository;
+use crate::utils::errors::AppResult;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 记录管理操作的审计日志，保存在 Redis 中供所有副本共享
+#[derive(Clone)]
+pub struct AuditService {
+    redis_repo: Arc<RedisRepository>,
+    max_entries: usize,
+}
+
+impl AuditService {
+    pub fn new(redis_repo: Arc<RedisRepository>, max_entries: usize) -> Self {
+        Self {
+            redis_repo,
+            max_entries,
+        }
+    }
+
+    pub async fn record(
+        &self,
+        action: AuditAction,
+        target: &str,
+        reason: Option<&str>,
+        detail: Option<String>,
+    ) -> AppResult<()> {
+        let entry = self.entry(action, target, reason, detail)?;
+        self.redis_repo.append_audit(&entry, self.max_entries).await
+    }
+
+    /// 生成序列化后的审计日志条目，由调用方与被审计的操作在同一事务中写入
+    pub fn entry(
+        &self,
+        action: AuditAction,
+        target: &str,
+        reason: Option<&str>,
+        detail: Option<String>,
+    ) -> AppResult<String> {
+        let entry = AuditEntry {
+            unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            action,
+            target: target.to_string(),
+            reason: reason.map(str::to_string),
+            detail,
+        };
+        tracing::warn!(
+            "Admin action {:?} on {} (reason: {})",
+            action,
+            target,
+            reason.unwrap_or("-")
+        );
+
+        Ok(serde_json::to_string(&entry)?)
+    }
+
+    /// 审计日志保留的最大条数
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// 最近的审计日志，按时间倒序
+    pub async fn list(&self, limit: usize) -> AppResult<Vec<AuditEntry>> {
+        self.redis_repo
+            .list_audit(limit.min(self.max_entries))
+            .await?
+            .iter()
+            .map(|entry| Ok(serde_json::from_str(entry)?))
+            .collect()
+    }
+}
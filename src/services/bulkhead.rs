@@ -0,0 +1,123 @@
+use crate::models::runtime::RuntimeStats;
+use crate::system::BulkheadConfig;
+use crate::utils::errors::{AppError, AppResult};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 上游并发隔离：限制全局与单个上游的并发请求数，超出时在有界队列中等待
+pub struct Bulkhead {
+    global: Arc<Semaphore>,
+    per_upstream: Mutex<HashMap<String, Arc<Semaphore>>>,
+    per_upstream_limit: usize,
+    max_queue: u64,
+    queue_timeout: Duration,
+    runtime_stats: Arc<RuntimeStats>,
+}
+
+/// 并发名额，释放时归还全局与上游名额
+pub struct BulkheadPermit {
+    _global: OwnedSemaphorePermit,
+    _upstream: OwnedSemaphorePermit,
+    runtime_stats: Arc<RuntimeStats>,
+}
+
+impl Drop for BulkheadPermit {
+    fn drop(&mut self) {
+        self.runtime_stats.decrement_upstream_in_flight();
+    }
+}
+
+/// 排队名额，释放时减少队列深度，等待中的请求被取消时同样会归还
+struct QueueSlot {
+    runtime_stats: Arc<RuntimeStats>,
+}
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        self.runtime_stats.decrement_upstream_queue_depth();
+    }
+}
+
+impl Bulkhead {
+    pub fn new(config: &BulkheadConfig, runtime_stats: Arc<RuntimeStats>) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(config.max_concurrent.max(1))),
+            per_upstream: Mutex::new(HashMap::new()),
+            per_upstream_limit: config.max_concurrent_per_upstream.max(1),
+            max_queue: config.max_queue as u64,
+            queue_timeout: Duration::from_millis(config.queue_timeout_ms),
+            runtime_stats,
+        }
+    }
+
+    /// 获取访问指定上游的并发名额，队列已满或等待超时返回 503
+    pub async fn acquire(&self, upstream: &str) -> AppResult<BulkheadPermit> {
+        let upstream_semaphore = self.upstream_semaphore(upstream);
+
+        // 快速路径：名额充足时无需排队
+        if let (Ok(upstream_permit), Ok(global_permit)) = (
+            upstream_semaphore.clone().try_acquire_owned(),
+            self.global.clone().try_acquire_owned(),
+        ) {
+            return Ok(self.permit(global_permit, upstream_permit));
+        }
+
+        if !self
+            .runtime_stats
+            .try_increment_upstream_queue_depth(self.max_queue)
+        {
+            return Err(self.reject(upstream, "queue is full"));
+        }
+        let queue_slot = QueueSlot {
+            runtime_stats: self.runtime_stats.clone(),
+        };
+        let acquired = tokio::time::timeout(self.queue_timeout, async {
+            let upstream_permit = upstream_semaphore.acquire_owned().await;
+            let global_permit = self.global.clone().acquire_owned().await;
+            (upstream_permit, global_permit)
+        })
+        .await;
+        drop(queue_slot);
+
+        match acquired {
+            Ok((Ok(upstream_permit), Ok(global_permit))) => {
+                Ok(self.permit(global_permit, upstream_permit))
+            }
+            Ok(_) => Err(AppError::Internal("Bulkhead semaphore closed".to_string())),
+            Err(_) => Err(self.reject(upstream, "queue wait timed out")),
+        }
+    }
+
+    fn upstream_semaphore(&self, upstream: &str) -> Arc<Semaphore> {
+        self.per_upstream
+            .lock()
+            .unwrap()
+            .entry(upstream.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_upstream_limit)))
+            .clone()
+    }
+
+    fn permit(
+        &self,
+        global: OwnedSemaphorePermit,
+        upstream: OwnedSemaphorePermit,
+    ) -> BulkheadPermit {
+        self.runtime_stats.increment_upstream_in_flight();
+        BulkheadPermit {
+            _global: global,
+            _upstream: upstream,
+            runtime_stats: self.runtime_stats.clone(),
+        }
+    }
+
+    fn reject(&self, upstream: &str, reason: &str) -> AppError {
+        self.runtime_stats.increment_upstream_rejected_count();
+        tracing::warn!("Bulkhead rejected request to {}: {}", upstream, reason);
+        AppError::ServiceUnavailable {
+            message: format!("Too many concurrent upstream requests to {}", upstream),
+            retry_after: Some(self.queue_timeout.as_secs().max(1)),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use crate::system::CacheKeyConfig;
+use crate::utils::errors::{AppError, AppResult};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+
+/// 缓存键版本前缀，键或缓存值的存储格式变化时递增，避免新旧格式的缓存条目混淆
+pub const CACHE_KEY_VERSION: &str = "v3";
+
+/// 缓存解密后内容时使用独立的缓存键，避免与密文条目混用
+pub const DECRYPTED_KEY_SUFFIX: &str = "#decrypted";
+
+/// 负责把请求路径规范化为稳定的缓存键
+#[derive(Clone)]
+pub struct CacheKeyBuilder {
+    ignored_params: HashSet<String>,
+    vary_headers: Vec<HeaderName>,
+    // 以 vary_secret 为密钥初始化的 HMAC，每次计算时克隆
+    vary_mac: Hmac<Sha256>,
+}
+
+impl CacheKeyBuilder {
+    pub fn new(key_config: &CacheKeyConfig, vary_headers: &[String]) -> AppResult<Self> {
+        let vary_headers = parse_header_names(vary_headers)?;
+        let secret = key_config
+            .vary_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty());
+        if !vary_headers.is_empty() && secret.is_none() {
+            return Err(AppError::Config(
+                "headers.vary requires cache_key.vary_secret".to_string(),
+            ));
+        }
+        let vary_mac = Hmac::<Sha256>::new_from_slice(secret.unwrap_or_default().as_bytes())
+            .map_err(|e| AppError::Config(format!("Invalid cache_key.vary_secret: {}", e)))?;
+
+        Ok(Self {
+            ignored_params: key_config
+                .ignored_params
+                .iter()
+                .map(|p| p.to_ascii_lowercase())
+                .collect(),
+            vary_headers,
+            vary_mac,
+        })
+    }
+
+    /// 参与缓存键计算的请求头，代理响应的 Vary 头需要列出
+    pub fn vary_header_names(&self) -> &[HeaderName] {
+        &self.vary_headers
+    }
+
+    /// 规范化路径与查询串：合并重复斜杠、解码安全的百分号转义、
+    /// 去除可忽略参数（如缓存破坏参数）并对剩余参数排序
+    pub fn normalize(&self, path_and_query: &str) -> String {
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+
+        let mut normalized = collapse_slashes(&normalize_escapes(path));
+
+        if let Some(query) = query {
+            let mut params: Vec<(String, String)> = query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((k, v)) => (normalize_escapes(k), normalize_escapes(v)),
+                    None => (normalize_escapes(pair), String::new()),
+                })
+                .filter(|(k, _)| !self.ignored_params.contains(&k.to_ascii_lowercase()))
+                .collect();
+            params.sort();
+
+            if !params.is_empty() {
+                let query = params
+                    .iter()
+                    .map(|(k, v)| {
+                        if v.is_empty() {
+                            k.clone()
+                        } else {
+                            format!("{}={}", k, v)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&");
+                normalized.push('?');
+                normalized.push_str(&query);
+            }
+        }
+
+        normalized
+    }
+
+    /// 由规范化路径和 vary 请求头构建缓存键，
+    /// vary 请求头的取值以服务端密钥计算 HMAC，避免从 Redis 键名暴力反推 Authorization 等低熵取值
+    pub fn build(&self, normalized_path: &str, headers: &HeaderMap) -> String {
+        let key = format!("{}:{}", CACHE_KEY_VERSION, normalized_path);
+        if self.vary_headers.is_empty() {
+            return key;
+        }
+
+        let vary_parts: Vec<String> = self
+            .vary_headers
+            .iter()
+            .map(|name| {
+                let mut mac = self.vary_mac.clone();
+                let mut present = false;
+                for value in headers.get_all(name) {
+                    mac.update(value.as_bytes());
+                    mac.update(b"\n");
+                    present = true;
+                }
+                if present {
+                    let digest = mac.finalize().into_bytes();
+                    format!("{}={}", name, &hex::encode(digest)[..16])
+                } else {
+                    format!("{}=-", name)
+                }
+            })
+            .collect();
+
+        format!("{}#vary:{}", key, vary_parts.join("&"))
+    }
+
+    /// 从显式传入的取值（请求头名称不区分大小写）构造 vary 请求头，
+    /// 供管理接口定位缓存键，其余名称忽略
+    pub fn vary_headers(&self, values: &HashMap<String, String>) -> AppResult<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for name in &self.vary_headers {
+            let Some((_, value)) = values
+                .iter()
+                .find(|(param, _)| param.eq_ignore_ascii_case(name.as_str()))
+            else {
+                continue;
+            };
+            let value = HeaderValue::from_str(value).map_err(|e| {
+                AppError::BadRequest(format!("Invalid value for header {}: {}", name, e))
+            })?;
+            headers.insert(name.clone(), value);
+        }
+        Ok(headers)
+    }
+}
+
+/// 从缓存键中取出规范化路径，不含 vary 等后缀
+pub fn path_from_key(key: &str) -> Option<&str> {
+    let path = key.strip_prefix(CACHE_KEY_VERSION)?.strip_prefix(':')?;
+    path.split('#').next()
+}
+
+pub fn parse_header_names(names: &[String]) -> AppResult<Vec<HeaderName>> {
+    names
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|e| AppError::Config(format!("Invalid header name '{}': {}", name, e)))
+        })
+        .collect()
+}
+
+/// 合并连续的斜杠
+fn collapse_slashes(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut last_slash = false;
+    for c in path.chars() {
+        if c == '/' {
+            if !last_slash {
+                result.push(c);
+            }
+            last_slash = true;
+        } else {
+            result.push(c);
+            last_slash = false;
+        }
+    }
+    result
+}
+
+/// 解码非保留字符（字母、数字、`-_~`）的百分号转义，其余转义统一为大写十六进制。
+/// `.` 保持编码，避免 `%2e%2e` 被解码为上级目录
+fn normalize_escapes(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = String::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Some(decoded) = decode_hex_pair(bytes[i + 1], bytes[i + 2])
+        {
+            if decoded.is_ascii_alphanumeric() || matches!(decoded, b'-' | b'_' | b'~') {
+                result.push(decoded as char);
+            } else {
+                result.push_str(&format!("%{:02X}", decoded));
+            }
+            i += 3;
+            continue;
+        }
+        // 保留原字符（包括多字节 UTF-8 字符）
+        let ch_len = utf8_char_len(bytes[i]);
+        result.push_str(&input[i..i + ch_len]);
+        i += ch_len;
+    }
+
+    result
+}
+
+fn decode_hex_pair(high: u8, low: u8) -> Option<u8> {
+    let high = (high as char).to_digit(16)?;
+    let low = (low as char).to_digit(16)?;
+    Some((high * 16 + low) as u8)
+}
+
+fn utf8_char_len(first_byte: u8) -> usize {
+    match first_byte {
+        b if b < 0x80 => 1,
+        b if b >= 0xF0 => 4,
+        b if b >= 0xE0 => 3,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(vary: &[&str], secret: Option<&str>) -> AppResult<CacheKeyBuilder> {
+        CacheKeyBuilder::new(
+            &CacheKeyConfig {
+                ignored_params: vec!["_".to_string()],
+                vary_secret: secret.map(str::to_string),
+            },
+            &vary.iter().map(|name| name.to_string()).collect::<Vec<_>>(),
+        )
+    }
+
+    fn authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn sorts_params_and_drops_ignored_ones() {
+        let builder = builder(&[], None).unwrap();
+        assert_eq!(
+            builder.normalize("/app.yml?b=2&a=1&_=1700000000&A=3"),
+            "/app.yml?A=3&a=1&b=2"
+        );
+        assert_eq!(builder.normalize("/app.yml?flag&a=1"), "/app.yml?a=1&flag");
+    }
+
+    #[test]
+    fn collapses_repeated_slashes() {
+        let builder = builder(&[], None).unwrap();
+        assert_eq!(builder.normalize("//team///app.yml"), "/team/app.yml");
+        assert_eq!(builder.normalize("/a//b.yml?x=1//2"), "/a/b.yml?x=1//2");
+    }
+
+    #[test]
+    fn decodes_only_unreserved_escapes() {
+        let builder = builder(&[], None).unwrap();
+        assert_eq!(builder.normalize("/%61pp-%7e%5F.yml"), "/app-~_.yml");
+        assert_eq!(builder.normalize("/app?v=%41"), "/app?v=A");
+        // `.` 保持编码，避免 `%2e%2e` 被解码为上级目录
+        assert_eq!(builder.normalize("/%2e%2e/app.yml"), "/%2E%2E/app.yml");
+        assert_eq!(builder.normalize("/./app.yml"), "/./app.yml");
+        // 保留字符与非 ASCII 字节统一为大写转义
+        assert_eq!(
+            builder.normalize("/a%2fb%3a.yml?k%3d=v%26w"),
+            "/a%2Fb%3A.yml?k%3D=v%26w"
+        );
+        assert_eq!(builder.normalize("/%e4%b8%ad.yml"), "/%E4%B8%AD.yml");
+        assert_eq!(builder.normalize("/中.yml"), "/中.yml");
+    }
+
+    #[test]
+    fn drops_empty_query_and_trailing_question_mark() {
+        let builder = builder(&[], None).unwrap();
+        assert_eq!(builder.normalize("/app.yml?"), "/app.yml");
+        assert_eq!(builder.normalize("/app.yml?&&"), "/app.yml");
+        assert_eq!(builder.normalize("/app.yml?_=1"), "/app.yml");
+    }
+
+    #[test]
+    fn keeps_invalid_escapes_without_panicking() {
+        let builder = builder(&[], None).unwrap();
+        for (input, expected) in [
+            ("/app%.yml", "/app%.yml"),
+            ("/app%zz.yml", "/app%zz.yml"),
+            ("/app%4", "/app%4"),
+            ("/app%", "/app%"),
+            ("/%中.yml", "/%中.yml"),
+            ("/app.yml?a=%", "/app.yml?a=%"),
+        ] {
+            assert_eq!(builder.normalize(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn requires_secret_for_vary_headers() {
+        assert!(builder(&["authorization"], None).is_err());
+        assert!(builder(&["authorization"], Some("")).is_err());
+        assert!(builder(&[], None).is_ok());
+    }
+
+    #[test]
+    fn keys_vary_values_with_secret() {
+        let builder_a = builder(&["authorization"], Some("secret-a")).unwrap();
+        let builder_b = builder(&["authorization"], Some("secret-b")).unwrap();
+        let basic = authorization("Basic dXNlcjpwYXNz");
+
+        let key = builder_a.build("/app.yml", &basic);
+        assert!(key.starts_with("v3:/app.yml#vary:authorization="));
+        assert!(!key.contains("dXNlcjpwYXNz"));
+        assert_eq!(key, builder_a.build("/app.yml", &basic));
+        assert_ne!(key, builder_b.build("/app.yml", &basic));
+        assert_ne!(
+            key,
+            builder_a.build("/app.yml", &authorization("Basic b3RoZXI6cGFzcw=="))
+        );
+        assert_eq!(
+            builder_a.build("/app.yml", &HeaderMap::new()),
+            "v3:/app.yml#vary:authorization=-"
+        );
+    }
+}
@@ -1,23 +1,164 @@
+use crate::models::history::PinRecord;
+use crate::models::overrides::OverrideRecord;
 use crate::repositories::redis_repository::RedisRepository;
+use crate::repositories::snapshot_repository::SnapshotRepository;
+use crate::system::HistoryConfig;
+use crate::utils::compression::EncodedBody;
 use crate::utils::errors::AppResult;
+use bytes::Bytes;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone)]
 pub struct CacheService {
     redis_repo: Arc<RedisRepository>,
+    stale_expire_seconds: u64,
+    history: HistoryConfig,
+    snapshot: Option<Arc<SnapshotRepository>>,
 }
 
 impl CacheService {
-    pub fn new(redis_repo: Arc<RedisRepository>) -> Self {
-        Self { redis_repo }
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        stale_expire_seconds: u64,
+        history: HistoryConfig,
+        snapshot: Option<Arc<SnapshotRepository>>,
+    ) -> Self {
+        Self {
+            redis_repo,
+            stale_expire_seconds,
+            history,
+            snapshot,
+        }
     }
 
-    pub async fn get(&self, key: &str) -> AppResult<Option<String>> {
-        self.redis_repo.get(key).await
+    /// 读取缓存，键被固定到历史版本时返回该版本的内容
+    pub async fn get(&self, key: &str) -> AppResult<Option<EncodedBody>> {
+        let (cached, pin) = self.redis_repo.get_with_pin(key).await?;
+        let Some(pin) = pin else {
+            return Ok(cached);
+        };
+        let pin: PinRecord = serde_json::from_str(&pin)?;
+
+        match self.redis_repo.get_version(key, &pin.hash).await? {
+            Some(pinned) => Ok(Some(pinned)),
+            None => {
+                tracing::warn!(
+                    "Pinned version {} of {} no longer exists, ignoring pin",
+                    pin.hash,
+                    key
+                );
+                Ok(cached)
+            }
+        }
+    }
+
+    /// 键被固定且固定的版本仍然存在时返回该版本的哈希，与 `get` 返回的内容一致
+    pub async fn pinned_hash(&self, key: &str) -> AppResult<Option<String>> {
+        let Some((record, _)) = self.redis_repo.get_pin(key).await? else {
+            return Ok(None);
+        };
+        let pin: PinRecord = serde_json::from_str(&record)?;
+        if !self.redis_repo.has_version(key, &pin.hash).await? {
+            return Ok(None);
+        }
+        Ok(Some(pin.hash))
+    }
+
+    /// 读取规范化路径的紧急覆盖内容
+    pub async fn get_override(&self, path: &str) -> AppResult<Option<Bytes>> {
+        let Some((record, _)) = self.redis_repo.get_override(path).await? else {
+            return Ok(None);
+        };
+        let record: OverrideRecord = serde_json::from_str(&record)?;
+        Ok(Some(Bytes::from(record.content)))
+    }
+
+    /// 读取版本历史中的内容
+    pub async fn get_version(&self, key: &str, hash: &str) -> AppResult<Option<EncodedBody>> {
+        self.redis_repo.get_version(key, hash).await
+    }
+
+    /// 读取兜底副本：先读 Redis 中的过期副本，没有或 Redis 不可用时读磁盘快照
+    pub async fn get_stale(&self, key: &str) -> AppResult<Option<EncodedBody>> {
+        let redis_error = match self.redis_repo.get_stale(key).await {
+            Ok(Some(stale)) => return Ok(Some(stale)),
+            Ok(None) => None,
+            Err(e) => Some(e),
+        };
+        let Some(snapshot) = &self.snapshot else {
+            return redis_error.map_or(Ok(None), Err);
+        };
+        if let Some(e) = redis_error {
+            tracing::warn!("Failed to read stale cache for {}: {}", key, e);
+        }
+
+        let content = snapshot.get(key).await?;
+        if content.is_some() {
+            tracing::warn!("Serving {} from disk snapshot", key);
+        }
+        Ok(content.map(EncodedBody::identity))
+    }
+
+    /// 写入缓存并记录版本历史，版本历史写入失败不影响缓存。`hash` 为内容哈希，与值一起保存
+    pub async fn set(
+        &self,
+        key: &str,
+        value: &[u8],
+        hash: &str,
+        expire_seconds: u64,
+    ) -> AppResult<()> {
+        self.redis_repo
+            .set(key, value, hash, expire_seconds, self.stale_expire_seconds)
+            .await?;
+
+        if self.history.enabled {
+            let timestamp_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            if let Err(e) = self.record_version(key, value, hash, timestamp_ms).await {
+                tracing::warn!("Failed to record version history for {}: {}", key, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_version(
+        &self,
+        key: &str,
+        value: &[u8],
+        hash: &str,
+        timestamp_ms: u64,
+    ) -> AppResult<()> {
+        let pinned = match self.redis_repo.get_pin(key).await? {
+            Some((record, ttl)) => {
+                let pin: PinRecord = serde_json::from_str(&record)?;
+                Some((pin.hash, ttl.max(0) as u64))
+            }
+            None => None,
+        };
+        self.redis_repo
+            .record_version(
+                key,
+                hash,
+                value,
+                timestamp_ms,
+                self.history.max_versions,
+                self.history.expire_time,
+                pinned.as_ref().map(|(hash, ttl)| (hash.as_str(), *ttl)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_keys(&self) -> AppResult<Vec<String>> {
+        self.redis_repo.list_cache_keys().await
     }
 
-    pub async fn set(&self, key: &str, value: &str, expire_seconds: u64) -> AppResult<()> {
-        self.redis_repo.set(key, value, expire_seconds).await
+    /// 使缓存键失效，过期副本仍然保留
+    pub async fn invalidate(&self, keys: &[String]) -> AppResult<usize> {
+        self.redis_repo.invalidate(keys).await
     }
 
     pub async fn clear_all(&self) -> AppResult<usize> {
@@ -0,0 +1,167 @@
+use crate::models::notifications::{ChangeEvent, ChangeSource};
+use crate::repositories::redis_repository::RedisRepository;
+use crate::system::NotificationConfig;
+use crate::utils::errors::{AppError, AppResult};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+// 订阅连接断开后的重连间隔上限
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// 本副本观察到的一次内容变化
+pub struct ObservedChange {
+    /// 变化前的内容哈希，缓存键首次记录时为空
+    pub previous_hash: Option<String>,
+}
+
+/// 配置变更通知：本副本观察到的变化发布到 Redis 频道，
+/// 所有副本订阅该频道后转发给本地的 SSE 订阅者
+pub struct ChangeNotifier {
+    redis_repo: Arc<RedisRepository>,
+    config: NotificationConfig,
+    sender: broadcast::Sender<ChangeEvent>,
+    stale_expire_seconds: u64,
+}
+
+impl ChangeNotifier {
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        config: NotificationConfig,
+        stale_expire_seconds: u64,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(config.buffer_size.max(1));
+        Self {
+            redis_repo,
+            config,
+            sender,
+            stale_expire_seconds,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn keep_alive(&self) -> Duration {
+        Duration::from_secs(self.config.keep_alive_secs.max(1))
+    }
+
+    /// 启动后台订阅任务，断线后按指数退避重连
+    pub fn start(&self, redis_url: &str) -> AppResult<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| AppError::Config(format!("Invalid Redis URL: {}", e)))?;
+        let channel = self.config.channel.clone();
+        let sender = self.sender.clone();
+
+        tokio::spawn(async move {
+            let mut delay = Duration::from_secs(1);
+            loop {
+                match client.get_async_pubsub().await {
+                    Ok(mut pubsub) => match pubsub.subscribe(&channel).await {
+                        Ok(()) => {
+                            tracing::info!("Subscribed to change channel {}", channel);
+                            delay = Duration::from_secs(1);
+                            let mut messages = pubsub.on_message();
+                            while let Some(message) = messages.next().await {
+                                let event = message
+                                    .get_payload::<String>()
+                                    .ok()
+                                    .and_then(|payload| serde_json::from_str(&payload).ok());
+                                match event {
+                                    // 没有订阅者时发送失败是正常情况
+                                    Some(event) => {
+                                        let _ = sender.send(event);
+                                    }
+                                    None => tracing::warn!("Ignoring malformed change event"),
+                                }
+                            }
+                            tracing::warn!("Change channel subscription closed, reconnecting");
+                        }
+                        Err(e) => tracing::warn!("Failed to subscribe to {}: {}", channel, e),
+                    },
+                    Err(e) => tracing::warn!("Failed to connect change subscriber: {}", e),
+                }
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn config(&self) -> &NotificationConfig {
+        &self.config
+    }
+
+    /// 缓存键最近一次刷新时记录的内容哈希
+    pub async fn current_hash(&self, key: &str) -> AppResult<Option<String>> {
+        self.redis_repo.current_hash(key).await
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 记录缓存键的最新内容哈希，内容变化时发布事件。未启用通知时只记录哈希。
+    /// 由本副本观察到这次变化时返回之前的哈希；首次记录的缓存键没有旧版本可对比，不发布事件。
+    /// 哈希与过期副本一同过期，副本过期后重新拉取视为首次记录
+    pub async fn observe(
+        &self,
+        key: &str,
+        path: &str,
+        hash: &str,
+        source: ChangeSource,
+    ) -> AppResult<Option<ObservedChange>> {
+        let event = change_event(path, Some(hash.to_string()), source);
+        let channel = if self.config.enabled {
+            self.config.channel.as_str()
+        } else {
+            ""
+        };
+        let previous = self
+            .redis_repo
+            .compare_and_publish(
+                key,
+                hash,
+                self.stale_expire_seconds,
+                channel,
+                &serde_json::to_string(&event)?,
+            )
+            .await?;
+        Ok(previous.map(|previous_hash| ObservedChange { previous_hash }))
+    }
+
+    /// 直接发布事件，用于管理操作等确定发生了变化的场景
+    pub async fn publish(
+        &self,
+        path: &str,
+        hash: Option<String>,
+        source: ChangeSource,
+    ) -> AppResult<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let event = change_event(path, hash, source);
+        self.redis_repo
+            .publish(&self.config.channel, &serde_json::to_string(&event)?)
+            .await
+    }
+}
+
+fn change_event(path: &str, hash: Option<String>, source: ChangeSource) -> ChangeEvent {
+    ChangeEvent {
+        path: path.to_string(),
+        hash,
+        source,
+        unix_time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    }
+}
@@ -0,0 +1,275 @@
+use crate::models::runtime::{CircuitBreakerInfo, CircuitState};
+use crate::system::CircuitBreakerConfig;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 上游请求熔断器，按滑动窗口内的失败率在关闭、打开、半开三种状态间切换
+pub struct CircuitBreaker {
+    upstream: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+struct BreakerInner {
+    state: CircuitState,
+    // 每次状态切换加一，用于识别在之前状态中放行的请求
+    generation: u64,
+    // 最近请求结果，true 表示失败
+    outcomes: VecDeque<bool>,
+    opened_at: Option<Instant>,
+    half_open_in_flight: u32,
+    half_open_successes: u32,
+    transition_count: u64,
+    last_transition_unix_time: u128,
+}
+
+/// 熔断器放行许可，请求完成后调用 `success` 或 `failure` 上报结果；
+/// 未上报即被丢弃（如请求被取消）时归还半开状态的试探名额。
+/// 许可记录放行时的状态代数，状态切换后才完成的请求结果会被忽略
+#[must_use]
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    generation: u64,
+    recorded: bool,
+}
+
+impl BreakerPermit<'_> {
+    pub fn success(mut self) {
+        self.recorded = true;
+        self.breaker.record(self.generation, false);
+    }
+
+    pub fn failure(mut self) {
+        self.recorded = true;
+        self.breaker.record(self.generation, true);
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.recorded {
+            return;
+        }
+        let mut inner = self.breaker.inner.lock().unwrap();
+        if inner.generation == self.generation && inner.state == CircuitState::HalfOpen {
+            inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(upstream: &str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            upstream: upstream.to_string(),
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                generation: 0,
+                outcomes: VecDeque::new(),
+                opened_at: None,
+                half_open_in_flight: 0,
+                half_open_successes: 0,
+                transition_count: 0,
+                last_transition_unix_time: 0,
+            }),
+        }
+    }
+
+    /// 申请放行；熔断打开时返回剩余冷却时间
+    pub fn try_acquire(&self) -> Result<BreakerPermit<'_>, Duration> {
+        if !self.config.enabled {
+            return Ok(BreakerPermit {
+                breaker: self,
+                generation: 0,
+                recorded: false,
+            });
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let cool_down = Duration::from_secs(self.config.open_duration_secs);
+
+        if inner.state == CircuitState::Open {
+            let elapsed = inner.opened_at.map(|t| t.elapsed()).unwrap_or(cool_down);
+            if elapsed < cool_down {
+                return Err(cool_down - elapsed);
+            }
+            self.transition(&mut inner, CircuitState::HalfOpen);
+        }
+
+        if inner.state == CircuitState::HalfOpen {
+            if inner.half_open_in_flight + inner.half_open_successes
+                >= self.config.half_open_max_calls.max(1)
+            {
+                return Err(Duration::from_secs(1));
+            }
+            inner.half_open_in_flight += 1;
+        }
+
+        Ok(BreakerPermit {
+            breaker: self,
+            generation: inner.generation,
+            recorded: false,
+        })
+    }
+
+    fn record(&self, generation: u64, failed: bool) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        // 在之前的状态中放行的请求（如半开期间才完成的关闭状态请求）不影响当前状态
+        if inner.generation != generation {
+            return;
+        }
+        match (inner.state, failed) {
+            (CircuitState::HalfOpen, true) => self.transition(&mut inner, CircuitState::Open),
+            (CircuitState::HalfOpen, false) => {
+                inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+                inner.half_open_successes += 1;
+                if inner.half_open_successes >= self.config.half_open_max_calls.max(1) {
+                    self.transition(&mut inner, CircuitState::Closed);
+                }
+            }
+            (CircuitState::Closed, _) => {
+                self.push_outcome(&mut inner, failed);
+                if failed
+                    && inner.outcomes.len() >= self.config.minimum_calls.max(1)
+                    && failure_rate(&inner.outcomes) >= self.config.failure_rate_threshold
+                {
+                    self.transition(&mut inner, CircuitState::Open);
+                }
+            }
+            // 打开状态不放行请求，不会有同一代数的结果
+            (CircuitState::Open, _) => {}
+        }
+    }
+
+    pub fn info(&self) -> CircuitBreakerInfo {
+        let inner = self.inner.lock().unwrap();
+        CircuitBreakerInfo {
+            enabled: self.config.enabled,
+            state: inner.state,
+            failure_rate: failure_rate(&inner.outcomes),
+            window_calls: inner.outcomes.len() as u64,
+            transition_count: inner.transition_count,
+            last_transition_unix_time: inner.last_transition_unix_time,
+        }
+    }
+
+    fn push_outcome(&self, inner: &mut BreakerInner, failed: bool) {
+        inner.outcomes.push_back(failed);
+        while inner.outcomes.len() > self.config.window_size.max(1) {
+            inner.outcomes.pop_front();
+        }
+    }
+
+    fn transition(&self, inner: &mut BreakerInner, next: CircuitState) {
+        tracing::warn!(
+            "Circuit breaker for {} transition: {:?} -> {:?} (failure rate {:.2})",
+            self.upstream,
+            inner.state,
+            next,
+            failure_rate(&inner.outcomes)
+        );
+
+        inner.state = next;
+        inner.generation += 1;
+        inner.transition_count += 1;
+        inner.last_transition_unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        inner.half_open_in_flight = 0;
+        inner.half_open_successes = 0;
+        match next {
+            CircuitState::Open => inner.opened_at = Some(Instant::now()),
+            CircuitState::Closed => {
+                inner.opened_at = None;
+                inner.outcomes.clear();
+            }
+            CircuitState::HalfOpen => {}
+        }
+    }
+}
+
+fn failure_rate(outcomes: &VecDeque<bool>) -> f64 {
+    if outcomes.is_empty() {
+        return 0.0;
+    }
+    outcomes.iter().filter(|failed| **failed).count() as f64 / outcomes.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(
+            "test",
+            CircuitBreakerConfig {
+                enabled: true,
+                window_size: 4,
+                minimum_calls: 2,
+                failure_rate_threshold: 0.5,
+                open_duration_secs: 0,
+                half_open_max_calls: 2,
+            },
+        )
+    }
+
+    fn trip(breaker: &CircuitBreaker) {
+        breaker.try_acquire().unwrap().failure();
+        breaker.try_acquire().unwrap().failure();
+        assert_eq!(breaker.info().state, CircuitState::Open);
+    }
+
+    #[test]
+    fn ignores_closed_permits_released_during_half_open() {
+        let breaker = breaker();
+        let stale_failure = breaker.try_acquire().unwrap();
+        let stale_success = breaker.try_acquire().unwrap();
+        trip(&breaker);
+
+        let probe = breaker.try_acquire().unwrap();
+        assert_eq!(breaker.info().state, CircuitState::HalfOpen);
+        stale_failure.failure();
+        stale_success.success();
+        assert_eq!(breaker.info().state, CircuitState::HalfOpen);
+
+        probe.success();
+        breaker.try_acquire().unwrap().success();
+        assert_eq!(breaker.info().state, CircuitState::Closed);
+    }
+
+    #[test]
+    fn ignores_probes_from_a_previous_half_open_round() {
+        let breaker = breaker();
+        trip(&breaker);
+
+        let failed = breaker.try_acquire().unwrap();
+        let stale = breaker.try_acquire().unwrap();
+        failed.failure();
+        assert_eq!(breaker.info().state, CircuitState::Open);
+
+        let probe = breaker.try_acquire().unwrap();
+        stale.success();
+        probe.success();
+        assert_eq!(breaker.info().state, CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn dropped_half_open_permit_returns_its_slot() {
+        let breaker = breaker();
+        trip(&breaker);
+
+        let first = breaker.try_acquire().unwrap();
+        let second = breaker.try_acquire().unwrap();
+        drop(second);
+        let third = breaker.try_acquire().unwrap();
+        first.success();
+        third.success();
+        assert_eq!(breaker.info().state, CircuitState::Closed);
+    }
+}
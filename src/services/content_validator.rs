@@ -0,0 +1,202 @@
+use crate::system::ValidationConfig;
+use crate::utils::config_format::ConfigFormat;
+use serde::Deserialize;
+
+/// 写入缓存前校验上游内容，避免把登录页、错误页或截断的文件缓存下来
+#[derive(Clone)]
+pub struct ContentValidator {
+    config: ValidationConfig,
+}
+
+impl ContentValidator {
+    pub fn new(config: ValidationConfig) -> Self {
+        Self { config }
+    }
+
+    /// 校验通过返回 Ok，否则返回拒绝原因
+    pub fn validate(
+        &self,
+        path: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), String> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        if let Some(content_type) = content_type {
+            let mime = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            if self
+                .config
+                .rejected_content_types
+                .iter()
+                .any(|rejected| rejected.eq_ignore_ascii_case(&mime))
+            {
+                return Err(format!("unexpected content type '{}'", mime));
+            }
+        }
+
+        // 没有扩展名的路径（如 Nacos 的 dataId）同样可能拿到网关或登录页返回的 HTML
+        if looks_like_html(body) {
+            return Err("body looks like an HTML page".to_string());
+        }
+
+        let Some(format) = ConfigFormat::from_path(path) else {
+            return Ok(());
+        };
+
+        let text = std::str::from_utf8(body).map_err(|e| format!("invalid UTF-8: {}", e))?;
+
+        match format {
+            ConfigFormat::Yaml => validate_yaml(body),
+            ConfigFormat::Json => serde_json::from_slice::<serde_json::Value>(body)
+                .map(|_| ())
+                .map_err(|e| format!("invalid JSON: {}", e)),
+            ConfigFormat::Properties => validate_properties(text),
+        }
+    }
+}
+
+fn looks_like_html(body: &[u8]) -> bool {
+    let body = body
+        .strip_prefix(b"\xEF\xBB\xBF")
+        .unwrap_or(body)
+        .trim_ascii_start();
+    let head = body[..body.len().min(64)].to_ascii_lowercase();
+    head.starts_with(b"<!doctype html") || head.starts_with(b"<html")
+}
+
+/// 逐个解析 YAML 文档，根节点必须是映射、序列或空文档
+fn validate_yaml(body: &[u8]) -> Result<(), String> {
+    for document in serde_yml::Deserializer::from_slice(body) {
+        let value =
+            serde_yml::Value::deserialize(document).map_err(|e| format!("invalid YAML: {}", e))?;
+        match value {
+            serde_yml::Value::Mapping(_)
+            | serde_yml::Value::Sequence(_)
+            | serde_yml::Value::Null => {}
+            _ => return Err("YAML document root is not a mapping".to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// 按 `java.util.Properties` 的加载规则校验：`\uXXXX` 转义必须完整，
+/// 文件不能以续行符结尾（通常说明内容被截断）
+fn validate_properties(text: &str) -> Result<(), String> {
+    let mut continuation = false;
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let is_continuation = continuation;
+        continuation = ends_with_continuation(trimmed);
+        if !is_continuation && (trimmed.is_empty() || trimmed.starts_with(['#', '!'])) {
+            continuation = false;
+            continue;
+        }
+
+        let mut chars = trimmed.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                continue;
+            }
+            if chars.next() == Some('u') {
+                let digits: String = chars.by_ref().take(4).collect();
+                if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!(
+                        "properties line {} has a malformed \\uXXXX escape",
+                        index + 1
+                    ));
+                }
+            }
+        }
+    }
+    if continuation {
+        return Err("properties file ends with a line continuation".to_string());
+    }
+    Ok(())
+}
+
+/// 行尾有奇数个反斜杠时下一行是续行
+fn ends_with_continuation(line: &str) -> bool {
+    line.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(path: &str, content_type: Option<&str>, body: &str) -> Result<(), String> {
+        ContentValidator::new(ValidationConfig::default()).validate(
+            path,
+            content_type,
+            body.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn accepts_valid_content() {
+        assert!(
+            validate(
+                "/app.yml",
+                Some("application/x-yaml"),
+                "a:\n  b: 1\n---\nc: 2\n"
+            )
+            .is_ok()
+        );
+        assert!(validate("/app.yml", None, "").is_ok());
+        assert!(validate("/app.json", Some("application/json"), r#"{"a":[1]}"#).is_ok());
+        assert!(
+            validate(
+                "/app.properties",
+                None,
+                "# c\na=\\u00e9\\\n  b\n=empty key\n"
+            )
+            .is_ok()
+        );
+        assert!(validate("/app", None, "any text").is_ok());
+    }
+
+    #[test]
+    fn rejects_html_pages() {
+        let page = "\n  <!DOCTYPE html><html><body>Login</body></html>";
+        assert!(validate("/app.yml", Some("text/html; charset=utf-8"), "a: 1").is_err());
+        assert!(validate("/app.yml", None, page).is_err());
+        assert!(validate("/app.json", None, "<HTML><body>502</body></HTML>").is_err());
+        // 没有扩展名的路径也会检查
+        assert!(validate("/app", None, page).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_json() {
+        assert!(validate("/app.json", None, r#"{"a": {"b": 1"#).is_err());
+        assert!(validate("/app.json", None, "").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_yaml() {
+        assert!(validate("/app.yml", None, "a: [1, 2\n").is_err());
+        assert!(validate("/app.yml", None, "a: 1\n  b: 2\n").is_err());
+        assert!(validate("/app.yml", None, "just a string").is_err());
+        assert!(validate("/app.yml", None, "a: 1\n---\n- x\n---\nplain").is_err());
+    }
+
+    #[test]
+    fn rejects_broken_properties() {
+        assert!(validate("/app.properties", None, "a=\\u00zz\n").is_err());
+        assert!(validate("/app.properties", None, "a=\\u12").is_err());
+        assert!(validate("/app.properties", None, "a=1\nb=2 \\").is_err());
+        assert!(validate("/app.properties", None, "a=1\nb=\\\\").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_utf8_for_config_files() {
+        let validator = ContentValidator::new(ValidationConfig::default());
+        assert!(validator.validate("/app.yml", None, b"a: \xff").is_err());
+        assert!(validator.validate("/app.bin", None, b"\xff\xfe").is_ok());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_yml::{Mapping, Value};
+
+/// 解析 YAML 内容，多文档时按顺序深度合并，后面的文档覆盖前面的同名键
+///
+/// 不识别 `spring.config.activate.on-profile`，所有文档都会参与合并；
+/// 按 profile 筛选文档由上游负责（Spring 配置中心和目录、Git 上游返回的已是筛选后的结果）
+pub fn parse_yaml(body: &[u8]) -> AppResult<Value> {
+    let mut merged = Value::Null;
+    for document in serde_yml::Deserializer::from_slice(body) {
+        let value = Value::deserialize(document)
+            .map_err(|e| AppError::Proxy(format!("Failed to parse YAML: {}", e)))?;
+        merge_yaml(&mut merged, value);
+    }
+    Ok(merged)
+}
+
+/// 逐个处理 YAML 文档后重新输出，保留多文档结构
+pub fn map_documents(body: &[u8], mut f: impl FnMut(&mut Value)) -> AppResult<Bytes> {
+    let mut documents = Vec::new();
+    for document in serde_yml::Deserializer::from_slice(body) {
+        let mut value = Value::deserialize(document)
+            .map_err(|e| AppError::Proxy(format!("Failed to parse YAML: {}", e)))?;
+        f(&mut value);
+        documents.push(
+            serde_yml::to_string(&value)
+                .map_err(|e| AppError::Internal(format!("Failed to render YAML: {}", e)))?,
+        );
+    }
+    Ok(Bytes::from(documents.join("---\n")))
+}
+
+/// 按内容的实际格式处理后以原格式重新输出：YAML 逐个文档处理，JSON 作为单个文档处理。
+/// properties 没有层级结构，无法按文档处理
+pub fn map_config(
+    body: &[u8],
+    format: ConfigFormat,
+    mut f: impl FnMut(&mut Value),
+) -> AppResult<Bytes> {
+    match format {
+        ConfigFormat::Yaml => map_documents(body, f),
+        ConfigFormat::Json => {
+            let json: serde_json::Value = serde_json::from_slice(body)
+                .map_err(|e| AppError::Proxy(format!("Failed to parse JSON: {}", e)))?;
+            let mut value = serde_yml::to_value(json)
+                .map_err(|e| AppError::Internal(format!("Failed to convert JSON: {}", e)))?;
+            f(&mut value);
+            render(&value, ConfigFormat::Json)
+        }
+        ConfigFormat::Properties => Err(AppError::Proxy(
+            "Properties content cannot be processed as a document".to_string(),
+        )),
+    }
+}
+
+/// 逐行处理 properties 的值，`f` 返回新值（未转义）时替换该行的值，其余内容原样保留。
+/// 续行按独立的行处理，只适合替换单行的值
+pub fn map_properties(body: &[u8], mut f: impl FnMut(&str) -> Option<String>) -> AppResult<Bytes> {
+    let text = std::str::from_utf8(body)
+        .map_err(|e| AppError::Proxy(format!("Invalid properties content: {}", e)))?;
+    let mut output = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[content.len()..];
+        let start = properties_value_start(content);
+        let replaced = if content.trim_start().starts_with(['#', '!']) {
+            None
+        } else {
+            f(&content[start..])
+        };
+        match replaced {
+            Some(value) => {
+                output.push_str(&content[..start]);
+                output.push_str(&escape_properties(&value, false));
+                output.push_str(ending);
+            }
+            None => output.push_str(line),
+        }
+    }
+    Ok(Bytes::from(output))
+}
+
+/// properties 行中值的起始位置：键在第一个未转义的 `=`、`:` 或空白处结束
+fn properties_value_start(line: &str) -> usize {
+    let bytes = line.as_bytes();
+    let is_blank = |b: u8| matches!(b, b' ' | b'\t' | b'\x0c');
+    let mut index = 0;
+    while index < bytes.len() && is_blank(bytes[index]) {
+        index += 1;
+    }
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'=' | b':' => break,
+            b if is_blank(b) => break,
+            _ => index += 1,
+        }
+    }
+    index = index.min(bytes.len());
+    while index < bytes.len() && is_blank(bytes[index]) {
+        index += 1;
+    }
+    if index < bytes.len() && matches!(bytes[index], b'=' | b':') {
+        index += 1;
+        while index < bytes.len() && is_blank(bytes[index]) {
+            index += 1;
+        }
+    }
+    index
+}
+
+/// 深度合并：映射逐键递归合并，其他类型直接覆盖
+pub fn merge_yaml(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base_map), Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 按 RFC 7396 JSON Merge Patch 应用补丁：空值删除键，映射递归合并，其他类型直接替换
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Mapping(patch_map) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_mapping() {
+        *target = Value::Mapping(Mapping::new());
+    }
+    let Value::Mapping(target_map) = target else {
+        return;
+    };
+    for (key, value) in patch_map {
+        if value.is_null() {
+            target_map.shift_remove(key);
+        } else {
+            let entry = target_map.entry(key.clone()).or_insert(Value::Null);
+            merge_patch(entry, value);
+        }
+    }
+}
+
+/// 把 YAML 内容渲染为目标格式
+pub fn render(value: &Value, format: ConfigFormat) -> AppResult<Bytes> {
+    match format {
+        ConfigFormat::Yaml => serde_yml::to_string(value)
+            .map(Bytes::from)
+            .map_err(|e| AppError::Internal(format!("Failed to render YAML: {}", e))),
+        ConfigFormat::Json => serde_json::to_vec_pretty(&to_json(value))
+            .map(Bytes::from)
+            .map_err(AppError::Serialization),
+        ConfigFormat::Properties => {
+            let mut output = String::new();
+            for (key, value) in flatten(value) {
+                output.push_str(&escape_properties(&key, true));
+                output.push('=');
+                output.push_str(&escape_properties(&value, false));
+                output.push('\n');
+            }
+            Ok(Bytes::from(output))
+        }
+    }
+}
+
+/// 按 Spring 的约定展平为键值对：映射使用点号，序列使用下标，如 `a.b[0].c`
+pub fn flatten(value: &Value) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    flatten_into(value, String::new(), &mut entries);
+    entries
+}
+
+fn flatten_into(value: &Value, prefix: String, entries: &mut Vec<(String, String)>) {
+    match value {
+        Value::Mapping(map) => {
+            if map.is_empty() && !prefix.is_empty() {
+                entries.push((prefix.clone(), String::new()));
+            }
+            for (key, value) in map {
+                let key = scalar_to_string(key);
+                let path = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_into(value, path, entries);
+            }
+        }
+        Value::Sequence(items) => {
+            if items.is_empty() && !prefix.is_empty() {
+                entries.push((prefix.clone(), String::new()));
+            }
+            for (index, item) in items.iter().enumerate() {
+                flatten_into(item, format!("{}[{}]", prefix, index), entries);
+            }
+        }
+        Value::Tagged(tagged) => flatten_into(&tagged.value, prefix, entries),
+        scalar => {
+            if !prefix.is_empty() {
+                entries.push((prefix, scalar_to_string(scalar)));
+            }
+        }
+    }
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Tagged(tagged) => scalar_to_string(&tagged.value),
+        other => serde_yml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// YAML 转 JSON，非字符串键转换为字符串
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                serde_json::Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                serde_json::Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(serde_json::Value::Number)
+                    .unwrap_or(serde_json::Value::Null)
+            }
+        }
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Sequence(items) => serde_json::Value::Array(items.iter().map(to_json).collect()),
+        Value::Mapping(map) => serde_json::Value::Object(mapping_to_json(map)),
+        Value::Tagged(tagged) => to_json(&tagged.value),
+    }
+}
+
+fn mapping_to_json(map: &Mapping) -> serde_json::Map<String, serde_json::Value> {
+    map.iter()
+        .map(|(key, value)| (scalar_to_string(key), to_json(value)))
+        .collect()
+}
+
+/// 按 Java properties 规则转义，非 ASCII 字符输出为 `\uXXXX`
+fn escape_properties(text: &str, is_key: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (index, c) in text.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x0c' => escaped.push_str("\\f"),
+            ' ' if is_key || index == 0 => escaped.push_str("\\ "),
+            '=' | ':' | '#' | '!' if is_key || index == 0 => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(yaml: &str) -> String {
+        let value = parse_yaml(yaml.as_bytes()).unwrap();
+        String::from_utf8(render(&value, ConfigFormat::Properties).unwrap().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn flattens_nested_maps_and_lists() {
+        let value = parse_yaml(
+            b"a:\n  b:\n    - c: 1\n      d: true\n    - plain\nserver:\n  port: 8080\n",
+        )
+        .unwrap();
+        assert_eq!(
+            flatten(&value),
+            vec![
+                ("a.b[0].c".to_string(), "1".to_string()),
+                ("a.b[0].d".to_string(), "true".to_string()),
+                ("a.b[1]".to_string(), "plain".to_string()),
+                ("server.port".to_string(), "8080".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_null_and_empty_values_as_empty_strings() {
+        assert_eq!(
+            properties("a: ~\nb: ''\nc: {}\nd: []\ne:\n  - ~\n"),
+            "a=\nb=\nc=\nd=\ne[0]=\n"
+        );
+    }
+
+    #[test]
+    fn escapes_properties_keys_and_values() {
+        assert_eq!(
+            properties("'a=b': 1\n'c:d': 2\n'e f': 3\n'#g': 4\n"),
+            "a\\=b=1\nc\\:d=2\ne\\ f=3\n\\#g=4\n"
+        );
+        // 值中只有开头的分隔符和空白需要转义
+        assert_eq!(
+            properties("k: ' =x:y#z'\nm: '=v'\n"),
+            "k=\\ =x:y#z\nm=\\=v\n"
+        );
+        assert_eq!(
+            properties("名称: 值\nemoji: \"\u{1F600}\"\n"),
+            "\\u540D\\u79F0=\\u503C\nemoji=\\uD83D\\uDE00\n"
+        );
+        assert_eq!(
+            properties("path: 'C:\\dir'\nmulti: \"a\\nb\"\n"),
+            "path=C:\\\\dir\nmulti=a\\nb\n"
+        );
+    }
+
+    #[test]
+    fn merges_all_documents_in_order() {
+        let value = parse_yaml(
+            b"a: 1\nb:\n  c: 2\n---\nspring:\n  config:\n    activate:\n      on-profile: dev\nb:\n  d: 3\na: 4\n",
+        )
+        .unwrap();
+        let entries = flatten(&value);
+        assert!(entries.contains(&("a".to_string(), "4".to_string())));
+        assert!(entries.contains(&("b.c".to_string(), "2".to_string())));
+        assert!(entries.contains(&("b.d".to_string(), "3".to_string())));
+    }
+
+    #[test]
+    fn maps_json_as_json() {
+        let body = map_config(br#"{"z":{"a":1},"b":null}"#, ConfigFormat::Json, |value| {
+            value["z"]["a"] = Value::from(2);
+        })
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value.to_string(), r#"{"z":{"a":2},"b":null}"#);
+        assert!(map_config(b"a=1", ConfigFormat::Properties, |_| {}).is_err());
+    }
+
+    #[test]
+    fn maps_properties_values_in_place() {
+        let body = map_properties(
+            b"a\\=b = x\n  key value\n! x\nempty\nc:x\\\n  x\n",
+            |value| (value == "x").then(|| " y:".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            &body[..],
+            b"a\\=b = \\ y:\n  key value\n! x\nempty\nc:x\\\n  x\n"
+        );
+    }
+}
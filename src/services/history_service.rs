@@ -0,0 +1,339 @@
+use crate::models::history::{
+    CacheEntryInfo, ChangeKind, ConfigVersion, PinInfo, PinRecord, PinRequest, PropertyChange,
+    StructuralDiff, VersionListResponse,
+};
+use crate::models::notifications::{ChangeSource, ConfigChangedEvent};
+use crate::models::overrides::AuditAction;
+use crate::repositories::redis_repository::RedisRepository;
+use crate::services::audit_service::AuditService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::format_converter;
+use crate::services::webhook_dispatcher::WebhookDispatcher;
+use crate::system::HistoryConfig;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 计算内容哈希，用于版本去重
+pub fn content_hash(value: &[u8]) -> String {
+    hex::encode(Sha256::digest(value))[..16].to_string()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffMode {
+    /// 统一格式的文本差异
+    #[default]
+    Unified,
+    /// 按展平后的配置键对比
+    Structural,
+}
+
+pub enum VersionDiff {
+    Unified(String),
+    Structural(StructuralDiff),
+}
+
+/// 查询配置版本历史、对比任意两个版本，以及把缓存键固定到历史版本
+#[derive(Clone)]
+pub struct HistoryService {
+    redis_repo: Arc<RedisRepository>,
+    config: HistoryConfig,
+    audit: Arc<AuditService>,
+    change_notifier: Arc<ChangeNotifier>,
+    webhooks: Arc<WebhookDispatcher>,
+}
+
+impl HistoryService {
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        config: HistoryConfig,
+        audit: Arc<AuditService>,
+        change_notifier: Arc<ChangeNotifier>,
+        webhooks: Arc<WebhookDispatcher>,
+    ) -> Self {
+        Self {
+            redis_repo,
+            config,
+            audit,
+            change_notifier,
+            webhooks,
+        }
+    }
+
+    pub async fn list(&self, key: &str) -> AppResult<VersionListResponse> {
+        let versions = self
+            .redis_repo
+            .list_versions(key)
+            .await?
+            .into_iter()
+            .map(|(hash, recorded_unix_time)| ConfigVersion {
+                hash,
+                recorded_unix_time,
+            })
+            .collect();
+
+        Ok(VersionListResponse {
+            key: key.to_string(),
+            versions,
+        })
+    }
+
+    /// 对比两个版本，未指定新版本时与最新版本对比
+    pub async fn diff(
+        &self,
+        key: &str,
+        from: &str,
+        to: Option<&str>,
+        mode: DiffMode,
+    ) -> AppResult<VersionDiff> {
+        let to = match to {
+            Some(to) => to.to_string(),
+            None => self
+                .redis_repo
+                .list_versions(key)
+                .await?
+                .into_iter()
+                .next()
+                .map(|(hash, _)| hash)
+                .ok_or_else(|| AppError::NotFound(format!("No versions recorded for {}", key)))?,
+        };
+
+        let old = self.load(key, from).await?;
+        let new = self.load(key, &to).await?;
+
+        match mode {
+            DiffMode::Unified => {
+                let old = String::from_utf8_lossy(&old);
+                let new = String::from_utf8_lossy(&new);
+                let diff = TextDiff::from_lines(old.as_ref(), new.as_ref())
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(from, &to)
+                    .to_string();
+                Ok(VersionDiff::Unified(diff))
+            }
+            DiffMode::Structural => Ok(VersionDiff::Structural(StructuralDiff {
+                changes: structural_changes(&old, &new)?,
+                from: from.to_string(),
+                to,
+            })),
+        }
+    }
+
+    /// 把缓存键固定到历史版本，固定期间直接返回该版本且不再访问上游。
+    /// `path` 为规范化路径，固定后向订阅者与外部 Webhook 发布固定版本的哈希
+    pub async fn pin(&self, key: &str, path: &str, request: PinRequest) -> AppResult<PinInfo> {
+        if request.duration_secs == 0 || request.duration_secs > self.config.max_pin_duration_secs {
+            return Err(AppError::BadRequest(format!(
+                "duration_secs must be between 1 and {}",
+                self.config.max_pin_duration_secs
+            )));
+        }
+        let pinned = self.load(key, &request.hash).await?;
+        let previous = self.effective_hash(key).await?;
+
+        let record = PinRecord {
+            hash: request.hash,
+            pinned_unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            reason: request.reason,
+        };
+        self.redis_repo
+            .set_pin(
+                key,
+                &record.hash,
+                &serde_json::to_string(&record)?,
+                request.duration_secs,
+            )
+            .await?;
+        self.audit
+            .record(
+                AuditAction::PinSet,
+                key,
+                record.reason.as_deref(),
+                Some(format!(
+                    "version {} for {}s",
+                    record.hash, request.duration_secs
+                )),
+            )
+            .await?;
+        self.notify(key, path, previous, Some((record.hash.clone(), pinned)))
+            .await;
+
+        Ok(pin_info(key, record, request.duration_secs as i64))
+    }
+
+    /// 解除固定，返回固定是否存在。解除后向订阅者与外部 Webhook 发布恢复后的内容哈希，
+    /// 缓存已过期时哈希未知，订阅者需要重新拉取
+    pub async fn unpin(&self, key: &str, path: &str, reason: Option<&str>) -> AppResult<bool> {
+        let previous = self.effective_hash(key).await?;
+        let removed = self.redis_repo.delete_pin(key).await?;
+        if removed {
+            self.audit
+                .record(AuditAction::PinRemoved, key, reason, None)
+                .await?;
+            let current = match self.redis_repo.get(key).await? {
+                Some(body) => {
+                    let body = body.decode()?;
+                    Some((content_hash(&body), body))
+                }
+                None => None,
+            };
+            self.notify(key, path, previous, current).await;
+        }
+        Ok(removed)
+    }
+
+    /// 缓存键当前对外返回内容的哈希：固定时为固定的版本，否则为最近一次刷新的哈希
+    async fn effective_hash(&self, key: &str) -> AppResult<Option<String>> {
+        if let Some((record, _)) = self.redis_repo.get_pin(key).await? {
+            let pin: PinRecord = serde_json::from_str(&record)?;
+            if self.redis_repo.has_version(key, &pin.hash).await? {
+                return Ok(Some(pin.hash));
+            }
+        }
+        self.change_notifier.current_hash(key).await
+    }
+
+    /// 固定变化后发布新的内容哈希，与之前的哈希不同时推送外部 Webhook；发布失败不影响固定操作
+    async fn notify(
+        &self,
+        key: &str,
+        path: &str,
+        previous: Option<String>,
+        current: Option<(String, Bytes)>,
+    ) {
+        let hash = current.as_ref().map(|(hash, _)| hash.clone());
+        if let Err(e) = self
+            .change_notifier
+            .publish(path, hash, ChangeSource::Pin)
+            .await
+        {
+            tracing::warn!("Failed to publish pin change for {}: {}", key, e);
+        }
+
+        let (Some(old_hash), Some((new_hash, body))) = (previous, current) else {
+            return;
+        };
+        if old_hash == new_hash {
+            return;
+        }
+        let event = ConfigChangedEvent {
+            path: path.to_string(),
+            key: key.to_string(),
+            old_hash,
+            new_hash,
+            source: ChangeSource::Pin,
+            diff: None,
+            unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        self.webhooks.notify(event, body);
+    }
+
+    pub async fn list_pins(&self) -> AppResult<Vec<PinInfo>> {
+        self.redis_repo
+            .list_pins()
+            .await?
+            .into_iter()
+            .map(|(key, record, ttl)| Ok(pin_info(&key, serde_json::from_str(&record)?, ttl)))
+            .collect()
+    }
+
+    /// 查看缓存键的缓存状态、固定情况与版本数量
+    pub async fn inspect(&self, key: &str) -> AppResult<CacheEntryInfo> {
+        let (ttl, stale_ttl) = self.redis_repo.cache_ttls(key).await?;
+        let current_hash = match self.redis_repo.get(key).await? {
+            Some(body) => Some(content_hash(&body.decode()?)),
+            None => None,
+        };
+        let pin = match self.redis_repo.get_pin(key).await? {
+            Some((record, ttl)) => Some(pin_info(key, serde_json::from_str(&record)?, ttl)),
+            None => None,
+        };
+        let version_count = self.redis_repo.list_versions(key).await?.len();
+
+        Ok(CacheEntryInfo {
+            key: key.to_string(),
+            ttl_seconds: (ttl >= 0).then_some(ttl),
+            stale_ttl_seconds: (stale_ttl >= 0).then_some(stale_ttl),
+            current_hash,
+            pin,
+            active_override: None,
+            version_count,
+        })
+    }
+
+    async fn load(&self, key: &str, hash: &str) -> AppResult<Bytes> {
+        self.redis_repo
+            .get_version(key, hash)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Version {} not found for {}", hash, key)))?
+            .decode()
+    }
+}
+
+fn pin_info(key: &str, record: PinRecord, expires_in_seconds: i64) -> PinInfo {
+    PinInfo {
+        key: key.to_string(),
+        hash: record.hash,
+        pinned_unix_time: record.pinned_unix_time,
+        expires_in_seconds,
+        reason: record.reason,
+    }
+}
+
+/// 展平两个 YAML 版本后逐键对比，保持旧版本的键顺序，新增的键排在最后
+pub fn structural_changes(old: &[u8], new: &[u8]) -> AppResult<Vec<PropertyChange>> {
+    let old_entries = format_converter::flatten(&format_converter::parse_yaml(old)?);
+    let new_entries = format_converter::flatten(&format_converter::parse_yaml(new)?);
+    let old_map: HashMap<&str, &str> = old_entries
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let new_map: HashMap<&str, &str> = new_entries
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+
+    let mut changes = Vec::new();
+    for (key, old_value) in &old_entries {
+        match new_map.get(key.as_str()) {
+            Some(new_value) if *new_value == old_value => {}
+            Some(new_value) => changes.push(PropertyChange {
+                key: key.clone(),
+                change: ChangeKind::Changed,
+                old_value: Some(old_value.clone()),
+                new_value: Some(new_value.to_string()),
+            }),
+            None => changes.push(PropertyChange {
+                key: key.clone(),
+                change: ChangeKind::Removed,
+                old_value: Some(old_value.clone()),
+                new_value: None,
+            }),
+        }
+    }
+    for (key, new_value) in &new_entries {
+        if !old_map.contains_key(key.as_str()) {
+            changes.push(PropertyChange {
+                key: key.clone(),
+                change: ChangeKind::Added,
+                old_value: None,
+                new_value: Some(new_value.clone()),
+            });
+        }
+    }
+
+    Ok(changes)
+}
@@ -1,2 +1,23 @@
+pub mod audit_service;
+pub mod bulkhead;
+pub mod cache_key;
 pub mod cache_service;
-pub mod proxy_service; 
\ No newline at end of file
+pub mod change_notifier;
+pub mod cipher_decryptor;
+pub mod circuit_breaker;
+pub mod content_validator;
+pub mod format_converter;
+pub mod history_service;
+pub mod monitor_service;
+pub mod offline_mode;
+pub mod overlay;
+pub mod override_service;
+pub mod proxy_service;
+pub mod route_table;
+pub mod snapshot_service;
+pub mod upstream;
+pub mod upstream_recorder;
+pub mod upstream_url;
+pub mod upstream_watcher;
+pub mod watch_service;
+pub mod webhook_dispatcher;
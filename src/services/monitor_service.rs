@@ -0,0 +1,515 @@
+use crate::models::notifications::ChangeSource;
+use crate::models::responses::MonitorResponse;
+use crate::services::cache_key::path_from_key;
+use crate::services::cache_service::CacheService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::offline_mode::OfflineMode;
+use crate::services::proxy_service::ProxyService;
+use crate::system::WebhookConfig;
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use urlencoding::decode;
+
+// 对所有应用生效的公共配置文件名
+const SHARED_APPLICATION: &str = "application";
+// GitHub push 负载最多携带的提交数，超出的提交不出现在负载中
+const MAX_PAYLOAD_COMMITS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provider {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl Provider {
+    /// 按事件请求头识别来源。Gitea 同时发送 GitHub 兼容的请求头，需优先判断
+    fn detect(headers: &HeaderMap) -> Option<(Self, String)> {
+        [
+            (Provider::Gitea, "x-gitea-event"),
+            (Provider::GitLab, "x-gitlab-event"),
+            (Provider::GitHub, "x-github-event"),
+        ]
+        .into_iter()
+        .find_map(|(provider, name)| {
+            let event = headers.get(name)?.to_str().ok()?;
+            Some((provider, event.to_string()))
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Provider::GitHub => "github",
+            Provider::GitLab => "gitlab",
+            Provider::Gitea => "gitea",
+        }
+    }
+
+    fn is_push(self, event: &str) -> bool {
+        match self {
+            Provider::GitHub | Provider::Gitea => event == "push",
+            Provider::GitLab => event == "Push Hook" || event == "Tag Push Hook",
+        }
+    }
+
+    /// GitHub 与 Gitea 使用 HMAC-SHA256 签名，GitLab 直接携带 Secret token
+    fn verify(self, headers: &HeaderMap, body: &[u8], secret: &str) -> AppResult<()> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| AppError::Unauthorized(format!("Missing {} header", name)))
+        };
+
+        let valid = match self {
+            Provider::GitHub => {
+                let signature = header("x-hub-signature-256")?;
+                verify_hmac(
+                    secret,
+                    body,
+                    signature.strip_prefix("sha256=").unwrap_or(""),
+                )
+            }
+            Provider::Gitea => verify_hmac(secret, body, header("x-gitea-signature")?),
+            Provider::GitLab => bool::from(
+                header("x-gitlab-token")?
+                    .as_bytes()
+                    .ct_eq(secret.as_bytes()),
+            ),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(AppError::Unauthorized(
+                "Invalid webhook signature".to_string(),
+            ))
+        }
+    }
+}
+
+/// 三家平台 push 事件的公共字段
+#[derive(Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+    // GitLab 提供的实际提交数，负载中的提交可能被截断
+    total_commits_count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    added: Option<Vec<String>>,
+    modified: Option<Vec<String>>,
+    removed: Option<Vec<String>>,
+}
+
+impl PushPayload {
+    /// 推送中变化的配置文件。提交列表为空、可能被截断或缺少文件列表时无法确定，返回 None
+    fn changed_files(self) -> Option<BTreeSet<String>> {
+        let truncated = self.commits.is_empty()
+            || self.commits.len() >= MAX_PAYLOAD_COMMITS
+            || self
+                .total_commits_count
+                .is_some_and(|total| total > self.commits.len());
+        if truncated {
+            return None;
+        }
+
+        let mut files = BTreeSet::new();
+        for commit in self.commits {
+            if commit.added.is_none() && commit.modified.is_none() && commit.removed.is_none() {
+                return None;
+            }
+            files.extend(
+                [commit.added, commit.modified, commit.removed]
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter(|file| ConfigFormat::from_path(file).is_some()),
+            );
+        }
+        Some(files)
+    }
+}
+
+/// 处理配置仓库的推送回调：把变化的文件映射为 Spring 的应用名与 profile，
+/// 只失效（或刷新）受影响的缓存键
+pub struct MonitorService {
+    cache_service: Arc<CacheService>,
+    proxy_service: Arc<ProxyService>,
+    change_notifier: Arc<ChangeNotifier>,
+    offline: Arc<OfflineMode>,
+    config: WebhookConfig,
+}
+
+impl MonitorService {
+    pub fn new(
+        cache_service: Arc<CacheService>,
+        proxy_service: Arc<ProxyService>,
+        change_notifier: Arc<ChangeNotifier>,
+        offline: Arc<OfflineMode>,
+        config: WebhookConfig,
+    ) -> Self {
+        Self {
+            cache_service,
+            proxy_service,
+            change_notifier,
+            offline,
+            config,
+        }
+    }
+
+    pub async fn handle(&self, headers: &HeaderMap, body: &[u8]) -> AppResult<MonitorResponse> {
+        let Some(secret) = self.config.secret.as_deref().filter(|s| !s.is_empty()) else {
+            return Err(AppError::Forbidden(
+                "Webhook is disabled, set webhook.secret to enable it".to_string(),
+            ));
+        };
+        let (provider, event) = Provider::detect(headers)
+            .ok_or_else(|| AppError::BadRequest("Unsupported webhook provider".to_string()))?;
+        provider.verify(headers, body, secret)?;
+
+        let mut response = MonitorResponse {
+            provider: provider.name().to_string(),
+            event,
+            label: None,
+            changed_files: Vec::new(),
+            truncated: false,
+            invalidated: Vec::new(),
+            refreshed: Vec::new(),
+        };
+        if !provider.is_push(&response.event) {
+            return Ok(response);
+        }
+
+        let payload: PushPayload = serde_json::from_slice(body)
+            .map_err(|e| AppError::BadRequest(format!("Invalid push payload: {}", e)))?;
+        response.label = payload.git_ref.as_deref().map(|git_ref| {
+            git_ref
+                .strip_prefix("refs/heads/")
+                .or_else(|| git_ref.strip_prefix("refs/tags/"))
+                .unwrap_or(git_ref)
+                .to_string()
+        });
+
+        // 文件列表不完整时无法判断影响范围，失效该分支的全部缓存
+        let files = payload.changed_files();
+        response.truncated = files.is_none();
+        let stems: Option<Vec<&str>> = files
+            .as_ref()
+            .map(|files| files.iter().filter_map(|file| file_stem(file)).collect());
+        if let Some(files) = &files {
+            response.changed_files = files.iter().cloned().collect();
+        }
+        if stems.as_ref().is_some_and(Vec::is_empty) {
+            return Ok(response);
+        }
+        // 离线模式冻结缓存，推送不失效任何条目
+        if self.offline.is_enabled() {
+            tracing::info!(
+                "Offline mode, ignoring push from {} to {:?}",
+                provider.name(),
+                response.label
+            );
+            return Ok(response);
+        }
+
+        let affected: Vec<String> = self
+            .cache_service
+            .list_keys()
+            .await?
+            .into_iter()
+            .filter(|key| {
+                path_from_key(key).is_some_and(|path| {
+                    label_matches(path, response.label.as_deref(), &self.config.labels)
+                        && file_stem(path).is_some_and(|cached| {
+                            let Some(stems) = &stems else {
+                                return true;
+                            };
+                            // 请求路径中的逗号等保留字符可能仍是百分号转义
+                            let cached = decode(cached).unwrap_or(Cow::Borrowed(cached));
+                            stems.iter().any(|changed| affects(changed, &cached))
+                        })
+                })
+            })
+            .collect();
+        if affected.is_empty() {
+            return Ok(response);
+        }
+
+        self.cache_service.invalidate(&affected).await?;
+        tracing::info!(
+            "Webhook from {} invalidated {} cache entries",
+            provider.name(),
+            affected.len()
+        );
+
+        let mut notify_paths = BTreeSet::new();
+        for key in &affected {
+            if self.config.refresh {
+                match self
+                    .proxy_service
+                    .refresh_key(key, ChangeSource::Webhook)
+                    .await
+                {
+                    // 刷新时内容有变化会由刷新流程发布事件
+                    Ok(true) => {
+                        response.refreshed.push(key.clone());
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Failed to refresh {} after webhook: {}", key, e),
+                }
+            }
+            if let Some(path) = path_from_key(key) {
+                notify_paths.insert(path.to_string());
+            }
+        }
+
+        // 仅失效的条目内容未知，通知订阅者重新拉取
+        for path in notify_paths {
+            if let Err(e) = self
+                .change_notifier
+                .publish(&path, None, ChangeSource::Webhook)
+                .await
+            {
+                tracing::warn!("Failed to publish webhook change for {}: {}", path, e);
+            }
+        }
+
+        response.invalidated = affected;
+        Ok(response)
+    }
+}
+
+fn verify_hmac(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// 去掉目录、查询串与扩展名后的文件名，即 Spring 的 `{application}-{profile}`
+fn file_stem(path: &str) -> Option<&str> {
+    let path = path.split('?').next()?;
+    let name = path.rsplit('/').next()?;
+    let (stem, _) = name.rsplit_once('.')?;
+    (!stem.is_empty()).then_some(stem)
+}
+
+/// 带标签的请求路径（`/{label}/{application}-{profile}.yml`）只受同一分支的推送影响。
+/// 路径首段也可能是目录（如 `/app/application.yml`），只有它是已知的标签时才按标签过滤；
+/// 不带标签的路径使用配置中心的默认分支，无法判断时视为受影响
+fn label_matches(path: &str, label: Option<&str>, known_labels: &[String]) -> bool {
+    let Some(label) = label else {
+        return true;
+    };
+    let segments: Vec<&str> = path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    match segments.as_slice() {
+        // Spring 用 `(_)` 代替分支名中的斜杠
+        [cached_label, _] => {
+            let cached_label = cached_label.replace("(_)", "/");
+            cached_label == label || !known_labels.contains(&cached_label)
+        }
+        _ => true,
+    }
+}
+
+/// 判断仓库中变化的文件是否影响缓存的 `{application}-{profiles}` 请求。
+/// 应用名与 profile 都可能含有 `-`，因此按所有可能的切分方式匹配
+fn affects(changed: &str, cached: &str) -> bool {
+    // application.yml 对所有应用生效
+    if changed == SHARED_APPLICATION {
+        return true;
+    }
+    // 不带 profile 的 {application}.yml 对该应用的所有 profile 生效
+    if cached == changed
+        || cached
+            .strip_prefix(changed)
+            .is_some_and(|rest| rest.starts_with('-'))
+    {
+        return true;
+    }
+
+    changed.match_indices('-').any(|(index, _)| {
+        let (application, profile) = (&changed[..index], &changed[index + 1..]);
+        if application == SHARED_APPLICATION {
+            // application-{profile}.yml 对激活了该 profile 的所有应用生效
+            cached
+                .match_indices('-')
+                .any(|(index, _)| has_profile(&cached[index + 1..], profile))
+        } else {
+            cached
+                .strip_prefix(application)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .is_some_and(|profiles| has_profile(profiles, profile))
+        }
+    })
+}
+
+fn has_profile(profiles: &str, profile: &str) -> bool {
+    profiles.split(',').any(|candidate| candidate == profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(json: &str) -> PushPayload {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn shared_application_affects_everything() {
+        assert!(affects("application", "app-dev"));
+        assert!(affects("application", "app"));
+    }
+
+    #[test]
+    fn application_file_affects_all_its_profiles() {
+        assert!(affects("app", "app"));
+        assert!(affects("app", "app-dev"));
+        assert!(affects("app", "app-dev,db"));
+        assert!(!affects("app", "other-dev"));
+        assert!(!affects("app", "application-dev"));
+    }
+
+    #[test]
+    fn profile_file_affects_matching_profiles_only() {
+        assert!(affects("app-dev", "app-dev"));
+        assert!(affects("app-dev", "app-db,dev"));
+        assert!(!affects("app-dev", "app-prod"));
+        assert!(!affects("app-dev", "other-dev"));
+        // 应用名含有 `-` 时按所有切分方式匹配
+        assert!(affects("order-service-prod", "order-service-prod"));
+        assert!(!affects("order-service-prod", "order-service-dev"));
+    }
+
+    #[test]
+    fn shared_profile_file_affects_every_application_with_profile() {
+        assert!(affects("application-prod", "app-prod"));
+        assert!(affects("application-prod", "order-service-dev,prod"));
+        assert!(!affects("application-prod", "app-dev"));
+    }
+
+    #[test]
+    fn unlabelled_paths_always_match() {
+        let labels = vec!["main".to_string(), "release".to_string()];
+        assert!(label_matches("/app-dev.yml", Some("main"), &labels));
+        assert!(label_matches("/app-dev.yml", Some("feature"), &labels));
+        assert!(label_matches("/app-dev.yml", None, &labels));
+    }
+
+    #[test]
+    fn labelled_paths_match_only_pushed_label() {
+        let labels = vec!["main".to_string(), "release/1.0".to_string()];
+        assert!(label_matches("/main/app-dev.yml", Some("main"), &labels));
+        assert!(!label_matches(
+            "/main/app-dev.yml",
+            Some("release/1.0"),
+            &labels
+        ));
+        assert!(label_matches(
+            "/release(_)1.0/app.yml",
+            Some("release/1.0"),
+            &labels
+        ));
+        assert!(!label_matches(
+            "/release(_)1.0/app.yml",
+            Some("main"),
+            &labels
+        ));
+    }
+
+    #[test]
+    fn unknown_first_segment_is_treated_as_directory() {
+        assert!(label_matches("/app/application.yml", Some("main"), &[]));
+        let labels = vec!["main".to_string()];
+        assert!(label_matches("/app/application.yml", Some("main"), &labels));
+        assert!(label_matches("/app/application.yml", Some("dev"), &labels));
+        assert!(label_matches("/a/b/app.yml", Some("dev"), &labels));
+    }
+
+    #[test]
+    fn verifies_hmac_signature() {
+        let body = b"{\"ref\":\"refs/heads/main\"}";
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        assert!(verify_hmac("secret", body, &signature));
+        assert!(!verify_hmac("other", body, &signature));
+        assert!(!verify_hmac("secret", b"tampered", &signature));
+        assert!(!verify_hmac("secret", body, "not-hex"));
+        assert!(!verify_hmac("secret", body, ""));
+    }
+
+    #[test]
+    fn collects_changed_config_files() {
+        let files = payload(
+            r#"{"ref":"refs/heads/main","commits":[
+                {"added":["app-dev.yml"],"modified":["README.md"],"removed":[]},
+                {"added":[],"modified":["application.properties"],"removed":["old.yaml"]}
+            ]}"#,
+        )
+        .changed_files()
+        .unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<_>>(),
+            ["app-dev.yml", "application.properties", "old.yaml"]
+        );
+    }
+
+    #[test]
+    fn incomplete_file_lists_are_unknown() {
+        // 没有提交
+        assert!(
+            payload(r#"{"ref":"refs/heads/main","commits":[]}"#)
+                .changed_files()
+                .is_none()
+        );
+        // 提交缺少文件列表
+        assert!(
+            payload(r#"{"ref":"refs/heads/main","commits":[{"id":"abc"}]}"#)
+                .changed_files()
+                .is_none()
+        );
+        // GitLab 的实际提交数多于负载中的提交
+        assert!(
+            payload(
+                r#"{"ref":"refs/heads/main","total_commits_count":3,
+                "commits":[{"added":[],"modified":["app.yml"],"removed":[]}]}"#
+            )
+            .changed_files()
+            .is_none()
+        );
+        // 达到 GitHub 的提交数上限
+        let commits = vec![r#"{"added":[],"modified":["app.yml"],"removed":[]}"#; 20].join(",");
+        assert!(
+            payload(&format!(r#"{{"commits":[{}]}}"#, commits))
+                .changed_files()
+                .is_none()
+        );
+    }
+}
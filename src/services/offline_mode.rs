@@ -0,0 +1,139 @@
+use crate::models::overrides::AuditAction;
+use crate::models::runtime::{OfflineRecord, OfflineRequest, OfflineStatus};
+use crate::repositories::redis_repository::RedisRepository;
+use crate::services::audit_service::AuditService;
+use crate::utils::errors::{AppError, AppResult};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 各副本同步管理员切换结果的间隔
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+// 审计日志中的操作对象
+const AUDIT_TARGET: &str = "offline";
+
+/// 离线（只读）模式开关。管理员的切换保存在 Redis 中，各副本定期同步；
+/// 进入离线模式时移除缓存的过期时间，退出时恢复
+pub struct OfflineMode {
+    enabled: AtomicBool,
+    configured: bool,
+    redis_repo: Arc<RedisRepository>,
+    audit: Arc<AuditService>,
+    cache_expire_seconds: u64,
+    stale_expire_seconds: u64,
+}
+
+impl OfflineMode {
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        audit: Arc<AuditService>,
+        configured: bool,
+        cache_expire_seconds: u64,
+        stale_expire_seconds: u64,
+    ) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            configured,
+            redis_repo,
+            audit,
+            cache_expire_seconds,
+            stale_expire_seconds,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 读取管理员的切换结果，没有时使用配置文件中的默认值
+    pub async fn sync(&self) -> AppResult<()> {
+        let enabled = match self.load().await? {
+            Some(record) => record.enabled,
+            None => self.configured,
+        };
+        self.apply(enabled).await
+    }
+
+    /// 启动后台任务，定期同步其他副本上的切换
+    pub fn start(self: &Arc<Self>) {
+        let offline = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SYNC_INTERVAL).await;
+                if let Err(e) = offline.sync().await {
+                    tracing::warn!("Failed to sync offline mode: {}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn status(&self) -> AppResult<OfflineStatus> {
+        let record = self.load().await?;
+        Ok(OfflineStatus {
+            enabled: self.is_enabled(),
+            configured: self.configured,
+            reason: record.as_ref().map(|record| record.reason.clone()),
+            updated_unix_time: record.map(|record| record.updated_unix_time),
+        })
+    }
+
+    pub async fn set(&self, request: OfflineRequest) -> AppResult<OfflineStatus> {
+        if request.reason.trim().is_empty() {
+            return Err(AppError::BadRequest("reason is required".to_string()));
+        }
+
+        let record = OfflineRecord {
+            enabled: request.enabled,
+            reason: request.reason,
+            updated_unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        self.redis_repo
+            .set_offline(&serde_json::to_string(&record)?)
+            .await?;
+        let action = if record.enabled {
+            AuditAction::OfflineEnabled
+        } else {
+            AuditAction::OfflineDisabled
+        };
+        self.audit
+            .record(action, AUDIT_TARGET, Some(&record.reason), None)
+            .await?;
+
+        self.apply(record.enabled).await?;
+        self.status().await
+    }
+
+    async fn load(&self) -> AppResult<Option<OfflineRecord>> {
+        match self.redis_repo.get_offline().await? {
+            Some(record) => Ok(Some(serde_json::from_str(&record)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 状态变化时调整缓存的过期时间，多个副本重复执行也不影响结果。
+    /// 调整成功后才切换状态，失败时下一次同步会重试
+    async fn apply(&self, enabled: bool) -> AppResult<()> {
+        if self.is_enabled() == enabled {
+            return Ok(());
+        }
+
+        if enabled {
+            let count = self.redis_repo.persist_cache().await?;
+            tracing::warn!("Entered offline mode, froze {} cache entries", count);
+        } else {
+            let count = self
+                .redis_repo
+                .restore_cache_ttl(self.cache_expire_seconds, self.stale_expire_seconds)
+                .await?;
+            tracing::warn!(
+                "Left offline mode, restored expiry of {} cache entries",
+                count
+            );
+        }
+        self.enabled.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+use crate::services::format_converter;
+use crate::system::{OverlayConfig, OverlayStrategy};
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use globset::{Glob, GlobMatcher};
+use serde_yml::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+struct Overlay {
+    matcher: GlobMatcher,
+    name: String,
+    strategy: OverlayStrategy,
+    patch: Value,
+    hash: [u8; 32],
+}
+
+/// 启动时加载的本地补丁，按路径匹配后依次应用到上游文档
+pub struct OverlaySet {
+    overlays: Vec<Overlay>,
+}
+
+impl OverlaySet {
+    pub fn new(configs: &[OverlayConfig]) -> AppResult<Self> {
+        let overlays = configs
+            .iter()
+            .map(|config| {
+                let matcher = Glob::new(&config.pattern)
+                    .map_err(|e| {
+                        AppError::Config(format!(
+                            "Invalid overlay pattern '{}': {}",
+                            config.pattern, e
+                        ))
+                    })?
+                    .compile_matcher();
+                let content = fs::read(&config.file).map_err(|e| {
+                    AppError::Config(format!("Failed to read overlay '{}': {}", config.file, e))
+                })?;
+                let patch = format_converter::parse_yaml(&content).map_err(|e| {
+                    AppError::Config(format!("Invalid overlay '{}': {}", config.file, e))
+                })?;
+                let name = config.name.clone().unwrap_or_else(|| {
+                    Path::new(&config.file)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| config.file.clone())
+                });
+
+                Ok(Overlay {
+                    matcher,
+                    name,
+                    strategy: config.strategy,
+                    patch,
+                    hash: Sha256::new()
+                        .chain_update([config.strategy as u8])
+                        .chain_update(&content)
+                        .finalize()
+                        .into(),
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(Self { overlays })
+    }
+
+    fn matching<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a Overlay> + 'a {
+        let file_path = path.split('?').next().unwrap_or_default();
+        self.overlays
+            .iter()
+            .filter(move |overlay| overlay.matcher.is_match(file_path))
+    }
+
+    /// 匹配路径的补丁来源名称，按应用顺序排列
+    pub fn sources(&self, path: &str) -> Vec<String> {
+        self.matching(path)
+            .map(|overlay| overlay.name.clone())
+            .collect()
+    }
+
+    /// 匹配补丁内容与策略的指纹，加入缓存键后补丁变更不会命中旧的缓存
+    pub fn fingerprint(&self, path: &str) -> Option<String> {
+        let mut hasher = Sha256::new();
+        let mut matched = false;
+        for overlay in self.matching(path) {
+            hasher.update(overlay.hash);
+            matched = true;
+        }
+        matched.then(|| hex::encode(hasher.finalize())[..16].to_string())
+    }
+
+    /// 依次应用匹配的补丁并以内容原来的格式输出。多文档 YAML 的每个文档都会应用补丁，
+    /// 保证无论客户端激活哪个 profile，补丁中的值都能生效；properties 内容不支持补丁。
+    /// 有补丁匹配时内容会被解析后重新渲染，原文中的注释、锚点与引号风格不会保留
+    pub fn apply(&self, path: &str, body: Bytes, format: ConfigFormat) -> AppResult<Bytes> {
+        let overlays: Vec<&Overlay> = self.matching(path).collect();
+        if overlays.is_empty() {
+            return Ok(body);
+        }
+
+        format_converter::map_config(&body, format, |document| {
+            for overlay in &overlays {
+                match overlay.strategy {
+                    OverlayStrategy::Merge => {
+                        format_converter::merge_yaml(document, overlay.patch.clone())
+                    }
+                    OverlayStrategy::MergePatch => {
+                        format_converter::merge_patch(document, &overlay.patch)
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlays(name: &str, patches: &[(&str, OverlayStrategy)]) -> OverlaySet {
+        let directory = std::env::temp_dir().join(format!(
+            "config-cache-proxy-overlays-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let configs: Vec<OverlayConfig> = patches
+            .iter()
+            .enumerate()
+            .map(|(index, (content, strategy))| {
+                let file = directory.join(format!("patch-{}.yml", index));
+                std::fs::write(&file, content).unwrap();
+                OverlayConfig {
+                    pattern: "/app/**".to_string(),
+                    file: file.to_string_lossy().into_owned(),
+                    strategy: *strategy,
+                    name: None,
+                }
+            })
+            .collect();
+        OverlaySet::new(&configs).unwrap()
+    }
+
+    fn apply(overlays: &OverlaySet, body: &str) -> Value {
+        let body = overlays
+            .apply(
+                "/app/a.yml",
+                Bytes::from(body.to_string()),
+                ConfigFormat::Yaml,
+            )
+            .unwrap();
+        serde_yml::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn merges_yaml_deeply() {
+        let overlays = overlays(
+            "merge",
+            &[(
+                "db:\n  url: jdbc:dc1\n  hosts: [c]\nremoved: ~\n",
+                OverlayStrategy::Merge,
+            )],
+        );
+        let value = apply(
+            &overlays,
+            "db:\n  url: jdbc:dev\n  pool: 10\n  hosts: [a, b]\nremoved: keep\n",
+        );
+        let expected: Value =
+            serde_yml::from_str("db:\n  url: jdbc:dc1\n  pool: 10\n  hosts: [c]\nremoved: keep\n")
+                .unwrap();
+        // 序列整体替换，null 不会删除已有的键
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn applies_json_merge_patch() {
+        let overlays = overlays(
+            "merge-patch",
+            &[(
+                r#"{"db": {"pool": null, "hosts": ["c"]}, "removed": null, "name": {"x": 1}}"#,
+                OverlayStrategy::MergePatch,
+            )],
+        );
+        let value = apply(
+            &overlays,
+            "db:\n  url: jdbc:dev\n  pool: 10\n  hosts: [a, b]\nremoved: keep\nname: app\n",
+        );
+        let expected: Value =
+            serde_yml::from_str("db:\n  url: jdbc:dev\n  hosts: [c]\nname:\n  x: 1\n").unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn applies_overlays_to_every_document_in_order() {
+        let overlays = overlays(
+            "documents",
+            &[
+                ("a: 1\nb: 1\n", OverlayStrategy::Merge),
+                ("b: 2\n", OverlayStrategy::MergePatch),
+            ],
+        );
+        let body = overlays
+            .apply(
+                "/app/a.yml",
+                Bytes::from("# comment\nx: 0\n---\nx: 1\n"),
+                ConfigFormat::Yaml,
+            )
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let documents: Vec<&str> = text.split("---\n").collect();
+        assert_eq!(documents, ["x: 0\na: 1\nb: 2\n", "x: 1\na: 1\nb: 2\n"]);
+        assert_eq!(
+            overlays.sources("/app/a.yml"),
+            ["patch-0.yml", "patch-1.yml"]
+        );
+
+        // 不匹配的路径原样返回
+        let body = Bytes::from("# comment\nx: 0\n");
+        assert_eq!(
+            overlays
+                .apply("/other.yml", body.clone(), ConfigFormat::Yaml)
+                .unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn fingerprint_follows_patch_content() {
+        let first = overlays("fingerprint-1", &[("a: 1", OverlayStrategy::Merge)]);
+        let second = overlays("fingerprint-2", &[("a: 2", OverlayStrategy::Merge)]);
+        assert_eq!(first.fingerprint("/other.yml"), None);
+        assert_ne!(
+            first.fingerprint("/app/a.yml"),
+            second.fingerprint("/app/a.yml")
+        );
+        assert_eq!(first.fingerprint("/app/a.yml").unwrap().len(), 16);
+        // 内容与策略都相同时指纹相同，只改变策略也会使用新的缓存键
+        let again = overlays("fingerprint-3", &[("a: 1", OverlayStrategy::Merge)]);
+        assert_eq!(
+            first.fingerprint("/app/a.yml"),
+            again.fingerprint("/app/a.yml")
+        );
+        let patch = overlays("fingerprint-4", &[("a: 1", OverlayStrategy::MergePatch)]);
+        assert_ne!(
+            first.fingerprint("/app/a.yml"),
+            patch.fingerprint("/app/a.yml")
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::models::notifications::ChangeSource;
+use crate::models::overrides::{AuditAction, OverrideInfo, OverrideRecord, OverrideRequest};
+use crate::repositories::redis_repository::RedisRepository;
+use crate::services::audit_service::AuditService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::format_converter;
+use crate::services::history_service::content_hash;
+use crate::utils::errors::{AppError, AppResult};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 管理员提供的紧急覆盖内容，优先于缓存和上游返回
+#[derive(Clone)]
+pub struct OverrideService {
+    redis_repo: Arc<RedisRepository>,
+    audit: Arc<AuditService>,
+    change_notifier: Arc<ChangeNotifier>,
+}
+
+impl OverrideService {
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        audit: Arc<AuditService>,
+        change_notifier: Arc<ChangeNotifier>,
+    ) -> Self {
+        Self {
+            redis_repo,
+            audit,
+            change_notifier,
+        }
+    }
+
+    /// 为规范化路径设置覆盖内容，内容必须是合法的 YAML
+    pub async fn set(&self, path: &str, request: OverrideRequest) -> AppResult<OverrideInfo> {
+        let reason = request.reason.trim();
+        if reason.is_empty() {
+            return Err(AppError::BadRequest("reason is required".to_string()));
+        }
+        if request.expires_in_secs == Some(0) {
+            return Err(AppError::BadRequest(
+                "expires_in_secs must be greater than 0".to_string(),
+            ));
+        }
+        format_converter::parse_yaml(request.content.as_bytes())
+            .map_err(|e| AppError::BadRequest(format!("Invalid override content: {}", e)))?;
+
+        let record = OverrideRecord {
+            content: request.content,
+            reason: reason.to_string(),
+            created_unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        let ttl = request.expires_in_secs.map_or(-1, |secs| secs as i64);
+        let serialized = serde_json::to_string(&record)?;
+        let info = override_info(path, record, ttl);
+        // 审计记录与覆盖内容在同一事务中写入，不会出现没有审计记录的覆盖
+        let audit_entry = self.audit.entry(
+            AuditAction::OverrideSet,
+            path,
+            Some(&info.reason),
+            Some(format!("hash {}, {} bytes", info.hash, info.size)),
+        )?;
+        self.redis_repo
+            .set_override(
+                path,
+                &serialized,
+                request.expires_in_secs,
+                &audit_entry,
+                self.audit.max_entries(),
+            )
+            .await?;
+        self.notify(path, Some(info.hash.clone())).await;
+        Ok(info)
+    }
+
+    /// 删除覆盖内容，返回覆盖是否存在
+    pub async fn remove(&self, path: &str, reason: Option<&str>) -> AppResult<bool> {
+        let audit_entry = self
+            .audit
+            .entry(AuditAction::OverrideRemoved, path, reason, None)?;
+        let removed = self
+            .redis_repo
+            .delete_override(path, &audit_entry, self.audit.max_entries())
+            .await?;
+        if removed {
+            // 删除覆盖后客户端需要重新拉取，新内容的哈希未知
+            self.notify(path, None).await;
+        }
+        Ok(removed)
+    }
+
+    async fn notify(&self, path: &str, hash: Option<String>) {
+        if let Err(e) = self
+            .change_notifier
+            .publish(path, hash, ChangeSource::Override)
+            .await
+        {
+            tracing::warn!("Failed to publish override change for {}: {}", path, e);
+        }
+    }
+
+    pub async fn get_info(&self, path: &str) -> AppResult<Option<OverrideInfo>> {
+        match self.redis_repo.get_override(path).await? {
+            Some((record, ttl)) => Ok(Some(override_info(
+                path,
+                serde_json::from_str(&record)?,
+                ttl,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list(&self) -> AppResult<Vec<OverrideInfo>> {
+        self.redis_repo
+            .list_overrides()
+            .await?
+            .into_iter()
+            .map(|(path, record, ttl)| {
+                Ok(override_info(&path, serde_json::from_str(&record)?, ttl))
+            })
+            .collect()
+    }
+}
+
+fn override_info(path: &str, record: OverrideRecord, ttl: i64) -> OverrideInfo {
+    OverrideInfo {
+        path: path.to_string(),
+        hash: content_hash(record.content.as_bytes()),
+        size: record.content.len(),
+        reason: record.reason,
+        created_unix_time: record.created_unix_time,
+        expires_in_seconds: (ttl >= 0).then_some(ttl),
+    }
+}
@@ -1,85 +1,885 @@
+use crate::models::notifications::{ChangeSource, ConfigChangedEvent};
+use crate::models::runtime::{CircuitBreakerInfo, RuntimeStats};
+use crate::services::bulkhead::Bulkhead;
+use crate::services::cache_key::{CacheKeyBuilder, DECRYPTED_KEY_SUFFIX, path_from_key};
 use crate::services::cache_service::CacheService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::cipher_decryptor::CipherDecryptor;
+use crate::services::circuit_breaker::CircuitBreaker;
+use crate::services::content_validator::ContentValidator;
+use crate::services::format_converter;
+use crate::services::history_service::content_hash;
+use crate::services::offline_mode::OfflineMode;
+use crate::services::overlay::OverlaySet;
+use crate::services::route_table::RouteTable;
+use crate::services::upstream::{Upstream, UpstreamSet};
+use crate::services::upstream_recorder::UpstreamRecorder;
+use crate::services::webhook_dispatcher::WebhookDispatcher;
+use crate::system::{BodyLimitConfig, OversizePolicy, RecordingMode, SystemConfig};
+use crate::utils::compression::EncodedBody;
+use crate::utils::config_format::ConfigFormat;
 use crate::utils::errors::{AppError, AppResult};
-use reqwest::Client;
+use axum::http::{HeaderMap, HeaderName, header};
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt, stream};
+use reqwest::{Client, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-static YML_EXT_SUFFIX: &str = ".yml";
+static YML_EXT: &str = "yml";
+// 匹配本地补丁时缓存键附带补丁指纹
+static OVERLAY_KEY_SUFFIX: &str = "#overlay:";
 
 #[derive(Clone)]
 pub struct ProxyService {
     cache_service: Arc<CacheService>,
-    http_client: Client,
-    proxy_base_url: String,
+    upstreams: Arc<UpstreamSet>,
     cache_expire_seconds: u64,
+    key_builder: CacheKeyBuilder,
+    // 按上游名称区分的熔断器，一个上游故障不影响其他上游
+    circuit_breakers: Arc<HashMap<String, CircuitBreaker>>,
+    bulkhead: Arc<Bulkhead>,
+    body_limit: BodyLimitConfig,
+    validator: ContentValidator,
+    routes: Arc<RouteTable>,
+    overlays: Arc<OverlaySet>,
+    decryptor: Option<Arc<CipherDecryptor>>,
+    change_notifier: Arc<ChangeNotifier>,
+    webhooks: Arc<WebhookDispatcher>,
+    offline: Arc<OfflineMode>,
+    recorder: Arc<UpstreamRecorder>,
+    runtime_stats: Arc<RuntimeStats>,
+}
+
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send>>;
+
+pub enum ProxyBody {
+    /// 完整响应体，来自缓存时可能保持压缩存储的编码
+    Full(EncodedBody),
+    /// 超出大小限制、未写入缓存的流式响应体
+    Stream(BodyStream),
 }
 
 pub struct ProxyResult {
-    pub content: String,
+    pub body: ProxyBody,
     pub from_cache: bool,
+    /// 是否返回了紧急覆盖内容，覆盖内容不计为缓存命中
+    pub from_override: bool,
+    /// 响应内容的格式
+    pub format: ConfigFormat,
+    /// 已应用的本地补丁来源
+    pub overlays: Vec<String>,
+    /// 规范内容（格式转换与响应时解密之前）的哈希，与 /watch 对比的哈希一致；流式转发时为空
+    pub hash: Option<String>,
+}
+
+enum UpstreamBody {
+    Complete(Bytes),
+    Oversize(BodyStream),
 }
 
 impl ProxyService {
     pub fn new(
         cache_service: Arc<CacheService>,
         http_client: Client,
-        proxy_base_url: String,
-        cache_expire_seconds: u64,
-    ) -> Self {
-        Self {
-            cache_service,
-            http_client,
-            proxy_base_url,
-            cache_expire_seconds,
+        runtime_stats: Arc<RuntimeStats>,
+        change_notifier: Arc<ChangeNotifier>,
+        offline: Arc<OfflineMode>,
+        webhooks: Arc<WebhookDispatcher>,
+        config: &SystemConfig,
+    ) -> AppResult<Self> {
+        let decryptor = CipherDecryptor::new(&config.encryption)?;
+        let decryptor = decryptor.is_configured().then(|| Arc::new(decryptor));
+        if decryptor.is_none() && config.routes.iter().any(|route| route.decrypt) {
+            return Err(AppError::Config(
+                "Routes require decryption but no encryption key is configured".to_string(),
+            ));
         }
+
+        let upstreams = UpstreamSet::new(config, http_client)?;
+        let circuit_breakers = upstreams
+            .upstreams()
+            .iter()
+            .map(|upstream| {
+                let name = upstream.name().to_string();
+                let breaker = CircuitBreaker::new(&name, config.circuit_breaker.clone());
+                (name, breaker)
+            })
+            .collect();
+
+        Ok(Self {
+            cache_service,
+            upstreams: Arc::new(upstreams),
+            cache_expire_seconds: config.redis.cache_expire_time,
+            key_builder: CacheKeyBuilder::new(&config.cache_key, &config.headers.vary)?,
+            circuit_breakers: Arc::new(circuit_breakers),
+            bulkhead: Arc::new(Bulkhead::new(&config.bulkhead, runtime_stats.clone())),
+            body_limit: config.body_limit.clone(),
+            validator: ContentValidator::new(config.validation.clone()),
+            routes: Arc::new(RouteTable::new(&config.routes)?),
+            overlays: Arc::new(OverlaySet::new(&config.overlays)?),
+            decryptor,
+            change_notifier,
+            webhooks,
+            offline,
+            recorder: Arc::new(UpstreamRecorder::new(
+                &config.recording,
+                config.body_limit.max_body_size,
+            )?),
+            runtime_stats,
+        })
+    }
+
+    pub fn upstreams(&self) -> Arc<UpstreamSet> {
+        self.upstreams.clone()
+    }
+
+    /// 默认上游的熔断器状态
+    pub fn circuit_breaker_info(&self) -> CircuitBreakerInfo {
+        self.circuit_breakers
+            .get(self.upstreams.default_upstream().name())
+            .map(CircuitBreaker::info)
+            .unwrap_or_default()
+    }
+
+    /// 所有上游的熔断器状态
+    pub fn upstream_circuit_breaker_infos(&self) -> BTreeMap<String, CircuitBreakerInfo> {
+        self.circuit_breakers
+            .iter()
+            .map(|(name, breaker)| (name.clone(), breaker.info()))
+            .collect()
     }
 
-    pub async fn proxy_request(&self, path: &str) -> AppResult<Option<ProxyResult>> {
-        // 只处理 yml 文件
-        if !path.ends_with(YML_EXT_SUFFIX) {
+    pub async fn proxy_request(
+        &self,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> AppResult<Option<ProxyResult>> {
+        let normalized = self.key_builder.normalize(path);
+
+        // 只处理配置文件
+        let Some((canonical, requested)) = self.canonical(&normalized) else {
             return Ok(None);
+        };
+        let raw = self.upstreams.find(&normalized).raw_paths();
+        let target = if raw {
+            requested
+        } else {
+            negotiate_format(requested, headers)
+        };
+
+        // json/properties 请求统一从对应的 yml 获取并缓存，再渲染为目标格式。
+        // 规范化形式只用于缓存键，上游仍使用原始路径与查询串
+        let upstream_path = if raw {
+            path.to_string()
+        } else {
+            canonical_yaml_path(path, requested)
+        };
+
+        let (decryptor, cache_decrypted) = self.decryption_for(&normalized);
+        let result = match decryptor {
+            Some(decryptor) if cache_decrypted => {
+                self.fetch_canonical(
+                    &canonical,
+                    &upstream_path,
+                    headers,
+                    Some(decryptor),
+                    ChangeSource::Refresh,
+                )
+                .await?
+            }
+            Some(decryptor) => {
+                let result = self
+                    .fetch_canonical(
+                        &canonical,
+                        &upstream_path,
+                        headers,
+                        None,
+                        ChangeSource::Refresh,
+                    )
+                    .await?;
+                decrypt_result(result, decryptor, content_format(&canonical))?
+            }
+            None => {
+                self.fetch_canonical(
+                    &canonical,
+                    &upstream_path,
+                    headers,
+                    None,
+                    ChangeSource::Refresh,
+                )
+                .await?
+            }
+        };
+        // 按原始路径读取的内容原样返回
+        if raw {
+            return Ok(Some(ProxyResult {
+                format: requested,
+                ..result
+            }));
+        }
+        convert_result(result, target).map(Some)
+    }
+
+    /// 计算请求路径对应的规范 yml 路径，紧急覆盖按该路径存放
+    pub fn canonical_path_for(&self, path: &str) -> AppResult<String> {
+        let normalized = self.key_builder.normalize(path);
+        let (canonical, _) = self.canonical(&normalized).ok_or_else(|| {
+            AppError::BadRequest(format!("Not a config file path: {}", normalized))
+        })?;
+        Ok(canonical)
+    }
+
+    /// 计算请求路径对应的缓存键，与代理请求实际使用的缓存键一致
+    pub fn cache_key_for(&self, path: &str, headers: &HeaderMap) -> AppResult<String> {
+        let canonical = self.canonical_path_for(path)?;
+        let (_, cache_decrypted) = self.decryption_for(&self.key_builder.normalize(path));
+        Ok(self.cache_key(&canonical, headers, cache_decrypted))
+    }
+
+    /// 参与缓存键计算的请求头
+    pub fn vary_header_names(&self) -> &[HeaderName] {
+        self.key_builder.vary_header_names()
+    }
+
+    /// 由管理接口显式传入的取值构造 vary 请求头
+    pub fn vary_headers(&self, values: &HashMap<String, String>) -> AppResult<HeaderMap> {
+        self.key_builder.vary_headers(values)
+    }
+
+    /// 重新拉取已失效的缓存键。vary 请求头的取值只以哈希形式保存在键中，
+    /// 无法还原请求时跳过，返回是否执行了刷新
+    pub async fn refresh_key(&self, cache_key: &str, source: ChangeSource) -> AppResult<bool> {
+        let Some(path) = path_from_key(cache_key) else {
+            return Ok(false);
+        };
+        if self.offline.is_enabled() {
+            return Ok(false);
+        }
+        let headers = HeaderMap::new();
+        let decryptor = self
+            .decryptor
+            .as_deref()
+            .filter(|_| cache_key.ends_with(DECRYPTED_KEY_SUFFIX));
+        if self.cache_key(path, &headers, decryptor.is_some()) != cache_key {
+            return Ok(false);
         }
 
-        let url = format!("{}{}", self.proxy_base_url, path);
+        self.fetch_canonical(path, path, &headers, decryptor, source)
+            .await?;
+        Ok(true)
+    }
+
+    /// 规范化路径对应的规范路径与请求的格式，非配置文件返回 None。
+    /// 按原始路径读取的上游（如 Nacos）不改写扩展名，没有扩展名的路径按 yml 返回
+    fn canonical(&self, normalized: &str) -> Option<(String, ConfigFormat)> {
+        if self.upstreams.find(normalized).raw_paths() {
+            let format = content_format(normalized);
+            return Some((normalized.to_string(), format));
+        }
+        let requested = ConfigFormat::from_path(normalized)?;
+        Some((canonical_yaml_path(normalized, requested), requested))
+    }
+
+    /// 按路由决定是否解密：默认缓存密文、每次响应时解密，只有显式允许时才缓存明文。
+    /// 返回解密器与是否在缓存前解密
+    fn decryption_for(&self, normalized: &str) -> (Option<&CipherDecryptor>, bool) {
+        let route = self.routes.find(normalized);
+        let decryptor = self
+            .decryptor
+            .as_deref()
+            .filter(|_| route.is_some_and(|route| route.decrypt));
+        let cache_decrypted =
+            decryptor.is_some() && route.is_some_and(|route| route.cache_decrypted);
+        (decryptor, cache_decrypted)
+    }
+
+    fn cache_key(&self, normalized: &str, headers: &HeaderMap, decrypted: bool) -> String {
+        decorate_cache_key(
+            self.key_builder.build(normalized, headers),
+            self.overlays.fingerprint(normalized),
+            decrypted,
+        )
+    }
+
+    /// 获取规范 YAML 内容：依次尝试紧急覆盖、缓存、上游与兜底副本；
+    /// 上游内容在写入缓存前应用本地补丁，传入解密器时还会在写入缓存前解密。
+    /// `normalized` 用于缓存键与路由匹配，`upstream_path` 是实际请求上游的路径
+    async fn fetch_canonical(
+        &self,
+        normalized: &str,
+        upstream_path: &str,
+        headers: &HeaderMap,
+        decryptor: Option<&CipherDecryptor>,
+        source: ChangeSource,
+    ) -> AppResult<ProxyResult> {
+        let upstream = self.upstreams.find(normalized);
+        let url = upstream.locate(upstream_path)?;
+        let format = content_format(normalized);
+        let cache_key = self.cache_key(normalized, headers, decryptor.is_some());
+        let overlays = self.overlays.sources(normalized);
+
+        // 紧急覆盖优先于缓存和上游；Redis 不可用时视为没有覆盖与缓存，继续访问上游或兜底副本
+        let override_content = self
+            .cache_service
+            .get_override(normalized)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to read override for {}: {}", normalized, e);
+                None
+            });
+        if let Some(content) = override_content {
+            let hash = content_hash(&content);
+            let content = match decryptor {
+                Some(decryptor) => decryptor.decrypt_document(&content, format)?,
+                None => content,
+            };
+            return Ok(ProxyResult {
+                body: ProxyBody::Full(EncodedBody::identity(content)),
+                from_cache: false,
+                from_override: true,
+                format: ConfigFormat::Yaml,
+                overlays: Vec::new(),
+                hash: Some(hash),
+            });
+        }
 
         // 检查缓存
-        if let Some(cached_response) = self.cache_service.get(&url).await? {
-            return Ok(Some(ProxyResult {
-                content: cached_response,
+        let cached = self
+            .cache_service
+            .get(&cache_key)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to read cache for {}: {}", cache_key, e);
+                None
+            });
+        if let Some(cached_response) = cached {
+            return Ok(ProxyResult {
+                hash: Some(body_hash(&cached_response)?),
+                body: ProxyBody::Full(cached_response),
                 from_cache: true,
-            }));
+                from_override: false,
+                format: ConfigFormat::Yaml,
+                overlays,
+            });
+        }
+
+        // 离线模式只使用过期副本与磁盘快照，从不访问上游
+        if self.offline.is_enabled() {
+            return match self.stale_result(&cache_key, &overlays).await? {
+                Some(stale) => Ok(stale),
+                None => Err(AppError::ServiceUnavailable {
+                    message: format!("Offline mode, no cached copy of {}", normalized),
+                    retry_after: None,
+                }),
+            };
+        }
+
+        // 回放模式下没有录制的路径直接返回 404，不计入熔断统计
+        if self.recorder.mode() == RecordingMode::Replay
+            && !self
+                .recorder
+                .has_recording(&self.key_builder.build(normalized, headers))
+                .await
+        {
+            return Err(AppError::NotFound(format!(
+                "No recording for {} in replay mode",
+                normalized
+            )));
         }
 
-        // 发送HTTP请求
-        let response = self
-            .http_client
-            .get(&url)
-            .send()
+        // 熔断打开时不访问上游，优先使用过期副本，否则快速失败
+        let circuit_breaker = self.circuit_breakers.get(upstream.name()).ok_or_else(|| {
+            AppError::Internal(format!("No circuit breaker for {}", upstream.name()))
+        })?;
+        let permit = match circuit_breaker.try_acquire() {
+            Ok(permit) => permit,
+            Err(retry_after) => {
+                if let Some(stale) = self.stale_result(&cache_key, &overlays).await? {
+                    tracing::warn!("Circuit open, serving stale cache for URL {}", url);
+                    return Ok(stale);
+                }
+                return Err(AppError::ServiceUnavailable {
+                    message: format!("Upstream circuit is open for URL: {}", url),
+                    retry_after: Some(retry_after.as_secs().max(1)),
+                });
+            }
+        };
+
+        // 获取上游并发名额，避免冷启动时大量请求同时打到配置中心
+        let bulkhead_permit = self.bulkhead.acquire(upstream.name()).await?;
+
+        // 请求上游，连接失败、超时和 5xx 计入熔断统计
+        let (content_type, body) = match self
+            .upstream_response(upstream.as_ref(), normalized, upstream_path, headers)
             .await
-            .map_err(AppError::HttpClient)?;
+        {
+            Ok(response) if response.status().is_success() => {
+                let content_type = response
+                    .headers()
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string);
+                let body = self.read_body(response).await;
+                match body {
+                    Err(AppError::HttpClient(_)) => permit.failure(),
+                    _ => permit.success(),
+                }
+                (content_type, body?)
+            }
+            Ok(response) => {
+                let error = AppError::Proxy(format!(
+                    "Upstream returned status: {} for URL: {}",
+                    response.status(),
+                    url
+                ));
+                if !response.status().is_server_error() {
+                    permit.success();
+                    return Err(error);
+                }
+                permit.failure();
+                return self.stale_or_error(&cache_key, &overlays, error).await;
+            }
+            Err(e) => {
+                permit.failure();
+                return self.stale_or_error(&cache_key, &overlays, e).await;
+            }
+        };
 
-        if !response.status().is_success() {
+        let body = match body {
+            UpstreamBody::Complete(body) => body,
+            UpstreamBody::Oversize(_) if decryptor.is_some() => {
+                return Err(AppError::Proxy(format!(
+                    "Upstream body for URL {} exceeds max_body_size and cannot be decrypted",
+                    url
+                )));
+            }
+            UpstreamBody::Oversize(_) if !overlays.is_empty() => {
+                return Err(AppError::Proxy(format!(
+                    "Upstream body for URL {} exceeds max_body_size and cannot be overlaid",
+                    url
+                )));
+            }
+            UpstreamBody::Oversize(body_stream) => {
+                tracing::warn!(
+                    "Upstream body for URL {} exceeds limit, streaming uncached",
+                    url
+                );
+                // 流式转发期间继续占用并发名额
+                let body_stream = body_stream.map(move |chunk| {
+                    let _ = &bulkhead_permit;
+                    chunk
+                });
+                return Ok(ProxyResult {
+                    body: ProxyBody::Stream(Box::pin(body_stream)),
+                    from_cache: false,
+                    from_override: false,
+                    format: ConfigFormat::Yaml,
+                    overlays: Vec::new(),
+                    hash: None,
+                });
+            }
+        };
+        drop(bulkhead_permit);
+
+        // 校验上游内容，校验失败时不缓存，优先返回上一份有效内容
+        if let Err(reason) = self
+            .validator
+            .validate(normalized, content_type.as_deref(), &body)
+        {
+            self.runtime_stats.increment_validation_rejected_count();
+            tracing::warn!("Rejected upstream content for URL {}: {}", url, reason);
+            if let Some(previous) = self.stale_result(&cache_key, &overlays).await? {
+                return Ok(previous);
+            }
             return Err(AppError::Proxy(format!(
-                "Upstream returned status: {} for URL: {}",
-                response.status(),
-                url
+                "Upstream content for URL {} failed validation: {}",
+                url, reason
             )));
         }
 
-        let response_text = response.text().await.map_err(AppError::HttpClient)?;
+        let body = self.overlays.apply(normalized, body, format)?;
+        let body = match decryptor {
+            Some(decryptor) => decryptor.decrypt_document(&body, format)?,
+            None => body,
+        };
 
-        // 缓存响应
+        // 缓存响应，内容哈希与值一起保存，命中时无需解压计算
+        let hash = content_hash(&body);
         if let Err(e) = self
             .cache_service
-            .set(&url, &response_text, self.cache_expire_seconds)
+            .set(&cache_key, &body, &hash, self.cache_expire_seconds)
             .await
         {
             // 缓存失败不应该影响主要业务流程，只记录错误
             tracing::warn!("Failed to cache response for URL {}: {}", url, e);
         }
 
-        Ok(Some(ProxyResult {
-            content: response_text,
+        // 内容变化时通知订阅者；只有观察到变化的副本推送外部 Webhook，
+        // 首次记录的缓存键没有旧版本可对比，不视为变更
+        match self
+            .change_notifier
+            .observe(&cache_key, normalized, &hash, source)
+            .await
+        {
+            Ok(Some(change)) => {
+                if let Some(old_hash) = change.previous_hash {
+                    let event = ConfigChangedEvent {
+                        path: normalized.to_string(),
+                        key: cache_key.clone(),
+                        old_hash,
+                        new_hash: hash.clone(),
+                        source,
+                        diff: None,
+                        unix_time: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_millis() as u64,
+                    };
+                    self.webhooks.notify(event, body.clone());
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to publish change for URL {}: {}", url, e),
+        }
+
+        Ok(ProxyResult {
+            body: ProxyBody::Full(EncodedBody::identity(body)),
             from_cache: false,
+            from_override: false,
+            format: ConfigFormat::Yaml,
+            overlays,
+            hash: Some(hash),
+        })
+    }
+
+    /// 上游不可用时优先返回过期副本或磁盘快照，都没有时返回原错误
+    async fn stale_or_error(
+        &self,
+        cache_key: &str,
+        overlays: &[String],
+        error: AppError,
+    ) -> AppResult<ProxyResult> {
+        match self.stale_result(cache_key, overlays).await? {
+            Some(stale) => {
+                tracing::warn!("{}, serving stale cache", error);
+                Ok(stale)
+            }
+            None => Err(error),
+        }
+    }
+
+    /// 读取过期副本作为兜底结果
+    async fn stale_result(
+        &self,
+        cache_key: &str,
+        overlays: &[String],
+    ) -> AppResult<Option<ProxyResult>> {
+        let Some(stale) = self.cache_service.get_stale(cache_key).await? else {
+            return Ok(None);
+        };
+        Ok(Some(ProxyResult {
+            hash: Some(body_hash(&stale)?),
+            body: ProxyBody::Full(stale),
+            from_cache: true,
+            from_override: false,
+            format: ConfigFormat::Yaml,
+            overlays: overlays.to_vec(),
         }))
     }
-} 
\ No newline at end of file
+
+    /// 读取上游响应体，超过大小限制时按策略拒绝或转为流式转发
+    async fn read_body(&self, response: Response) -> AppResult<UpstreamBody> {
+        let limit = self.body_limit.max_body_size;
+        let policy = self.body_limit.oversize_policy;
+        let oversize = || {
+            AppError::Proxy(format!(
+                "Upstream body exceeds max_body_size of {} bytes",
+                limit
+            ))
+        };
+
+        // 声明了 Content-Length 时无需读取即可判断
+        let declared_oversize = response.content_length().is_some_and(|len| len > limit);
+        let mut body_stream = response.bytes_stream();
+
+        if declared_oversize {
+            return match policy {
+                OversizePolicy::Reject => Err(oversize()),
+                OversizePolicy::Stream => Ok(UpstreamBody::Oversize(Box::pin(
+                    body_stream.map(|chunk| chunk.map_err(std::io::Error::other)),
+                ))),
+            };
+        }
+
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = body_stream.next().await {
+            let chunk = chunk.map_err(AppError::HttpClient)?;
+            if (buffer.len() + chunk.len()) as u64 > limit {
+                return match policy {
+                    OversizePolicy::Reject => Err(oversize()),
+                    OversizePolicy::Stream => {
+                        // 已读取的部分与剩余部分拼接后继续转发
+                        let head = stream::iter([Ok(buffer.freeze()), Ok(chunk)]);
+                        let rest = body_stream.map(|chunk| chunk.map_err(std::io::Error::other));
+                        Ok(UpstreamBody::Oversize(Box::pin(head.chain(rest))))
+                    }
+                };
+            }
+            buffer.extend_from_slice(&chunk);
+        }
+
+        Ok(UpstreamBody::Complete(buffer.freeze()))
+    }
+
+    /// 按录制模式获取上游响应：回放模式读取录制文件，录制模式在返回前写入录制文件。
+    /// 录制按规范化路径与 vary 请求头区分，与缓存键一致
+    async fn upstream_response(
+        &self,
+        upstream: &dyn Upstream,
+        normalized: &str,
+        upstream_path: &str,
+        headers: &HeaderMap,
+    ) -> AppResult<Response> {
+        let recording_key = || self.key_builder.build(normalized, headers);
+        match self.recorder.mode() {
+            RecordingMode::Off => upstream.fetch(upstream_path, headers).await,
+            RecordingMode::Record => {
+                let response = upstream.fetch(upstream_path, headers).await?;
+                self.recorder
+                    .record(normalized, &recording_key(), response)
+                    .await
+            }
+            RecordingMode::Replay => self.recorder.replay(&recording_key()).await,
+        }
+    }
+}
+
+/// 请求 yml/yaml 时按 Accept 头协商输出格式，其余按扩展名决定。
+/// 在可识别的媒体类型中取 q 值最高的一个，q 值相同时取靠前的，q=0 表示不接受
+fn negotiate_format(requested: ConfigFormat, headers: &HeaderMap) -> ConfigFormat {
+    if requested != ConfigFormat::Yaml {
+        return requested;
+    }
+
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return requested;
+    };
+
+    let mut best: Option<(ConfigFormat, f32)> = None;
+    for media_range in accept.split(',') {
+        let mut params = media_range.split(';');
+        let media_type = params.next().unwrap_or_default().trim();
+        let format = match media_type.to_ascii_lowercase().as_str() {
+            "application/json" => ConfigFormat::Json,
+            "text/x-java-properties" | "text/x-properties" => ConfigFormat::Properties,
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+                ConfigFormat::Yaml
+            }
+            _ => continue,
+        };
+        let quality = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .map(|(_, value)| value.trim().parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+        if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((format, quality));
+        }
+    }
+    best.map(|(format, _)| format).unwrap_or(requested)
+}
+
+/// 把 json/properties 请求路径的扩展名替换为 yml
+fn canonical_yaml_path(normalized: &str, requested: ConfigFormat) -> String {
+    if requested == ConfigFormat::Yaml {
+        return normalized.to_string();
+    }
+
+    let (path, query) = match normalized.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (normalized, None),
+    };
+    let stem = path.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(path);
+    match query {
+        Some(query) => format!("{}.{}?{}", stem, YML_EXT, query),
+        None => format!("{}.{}", stem, YML_EXT),
+    }
+}
+
+/// 在缓存键后追加补丁指纹与解密标记：补丁内容变化后使用新的缓存键，不会读到按旧补丁生成的内容
+fn decorate_cache_key(
+    mut cache_key: String,
+    fingerprint: Option<String>,
+    decrypted: bool,
+) -> String {
+    if let Some(fingerprint) = fingerprint {
+        cache_key.push_str(OVERLAY_KEY_SUFFIX);
+        cache_key.push_str(&fingerprint);
+    }
+    if decrypted {
+        cache_key.push_str(DECRYPTED_KEY_SUFFIX);
+    }
+    cache_key
+}
+
+/// 规范路径上内容的实际格式：改写为 yml 的路径总是 YAML，
+/// 按原始路径读取的上游按扩展名识别，没有扩展名时按 YAML 处理
+fn content_format(canonical: &str) -> ConfigFormat {
+    ConfigFormat::from_path(canonical).unwrap_or(ConfigFormat::Yaml)
+}
+
+/// 把规范 YAML 结果渲染为目标格式
+fn convert_result(result: ProxyResult, target: ConfigFormat) -> AppResult<ProxyResult> {
+    if target == result.format {
+        return Ok(result);
+    }
+
+    let ProxyBody::Full(body) = result.body else {
+        return Err(AppError::Proxy(
+            "Upstream body exceeds max_body_size and cannot be converted".to_string(),
+        ));
+    };
+    let value = format_converter::parse_yaml(&body.decode()?)?;
+    let rendered = format_converter::render(&value, target)?;
+
+    Ok(ProxyResult {
+        body: ProxyBody::Full(EncodedBody::identity(rendered)),
+        from_cache: result.from_cache,
+        from_override: result.from_override,
+        format: target,
+        overlays: result.overlays,
+        hash: result.hash,
+    })
+}
+
+/// 缓存值的内容哈希：优先使用写入时保存的哈希，没有保存哈希的旧值与磁盘快照才解码计算
+fn body_hash(body: &EncodedBody) -> AppResult<String> {
+    match &body.hash {
+        Some(hash) => Ok(hash.clone()),
+        None => Ok(content_hash(&body.clone().decode()?)),
+    }
+}
+
+/// 解密结果中的 `{cipher}` 值，解密后的内容不写入缓存
+fn decrypt_result(
+    result: ProxyResult,
+    decryptor: &CipherDecryptor,
+    format: ConfigFormat,
+) -> AppResult<ProxyResult> {
+    let ProxyBody::Full(body) = result.body else {
+        return Err(AppError::Proxy(
+            "Upstream body exceeds max_body_size and cannot be decrypted".to_string(),
+        ));
+    };
+    let decrypted = decryptor.decrypt_document(&body.decode()?, format)?;
+
+    Ok(ProxyResult {
+        body: ProxyBody::Full(EncodedBody::identity(decrypted)),
+        from_cache: result.from_cache,
+        from_override: result.from_override,
+        format: result.format,
+        overlays: result.overlays,
+        hash: result.hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::OverlayConfig;
+
+    fn negotiate(accept: &str) -> ConfigFormat {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, accept.parse().unwrap());
+        negotiate_format(ConfigFormat::Yaml, &headers)
+    }
+
+    #[test]
+    fn negotiates_by_quality() {
+        assert_eq!(negotiate("application/json"), ConfigFormat::Json);
+        assert_eq!(
+            negotiate("application/json;q=0.5, text/yaml"),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            negotiate("text/yaml;q=0.2, text/x-java-properties;q=0.8"),
+            ConfigFormat::Properties
+        );
+        assert_eq!(
+            negotiate("application/json; charset=utf-8; q=0.9, */*;q=0.1"),
+            ConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn keeps_first_on_equal_quality() {
+        assert_eq!(negotiate("application/json, text/yaml"), ConfigFormat::Json);
+        assert_eq!(
+            negotiate("text/yaml;q=0.5, application/json;q=0.5"),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn ignores_rejected_and_unknown_types() {
+        assert_eq!(negotiate("application/json;q=0"), ConfigFormat::Yaml);
+        assert_eq!(negotiate("text/html, */*"), ConfigFormat::Yaml);
+        assert_eq!(negotiate("application/json;q=oops"), ConfigFormat::Yaml);
+    }
+
+    #[test]
+    fn keeps_extension_format() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, "text/yaml".parse().unwrap());
+        assert_eq!(
+            negotiate_format(ConfigFormat::Json, &headers),
+            ConfigFormat::Json
+        );
+    }
+
+    #[test]
+    fn overlay_fingerprint_changes_cache_key() {
+        let directory = std::env::temp_dir().join(format!(
+            "config-cache-proxy-overlay-key-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let overlays = |content: &str| {
+            let file = directory.join("patch.yml");
+            std::fs::write(&file, content).unwrap();
+            OverlaySet::new(&[OverlayConfig {
+                pattern: "/app/**".to_string(),
+                file: file.to_string_lossy().into_owned(),
+                strategy: Default::default(),
+                name: None,
+            }])
+            .unwrap()
+        };
+        let key = |overlays: &OverlaySet, path: &str, decrypted: bool| {
+            decorate_cache_key(
+                format!("v3:{}", path),
+                overlays.fingerprint(path),
+                decrypted,
+            )
+        };
+
+        let first = overlays("a: 1");
+        let second = overlays("a: 2");
+        assert_eq!(key(&first, "/other.yml", false), "v3:/other.yml");
+        let patched = key(&first, "/app/a.yml", false);
+        assert!(patched.starts_with("v3:/app/a.yml#overlay:"));
+        assert_eq!(path_from_key(&patched), Some("/app/a.yml"));
+        assert_ne!(patched, key(&second, "/app/a.yml", false));
+        assert_eq!(patched, key(&overlays("a: 1"), "/app/a.yml", false));
+        assert_eq!(
+            key(&first, "/app/a.yml", true),
+            format!("{}{}", patched, DECRYPTED_KEY_SUFFIX)
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::system::RouteConfig;
+use crate::utils::errors::{AppError, AppResult};
+use globset::{Glob, GlobMatcher};
+
+/// 按配置顺序匹配请求路径，返回第一条命中的路由规则
+pub struct RouteTable {
+    routes: Vec<(GlobMatcher, RouteConfig)>,
+}
+
+impl RouteTable {
+    pub fn new(routes: &[RouteConfig]) -> AppResult<Self> {
+        let routes = routes
+            .iter()
+            .map(|route| {
+                let matcher = Glob::new(&route.pattern)
+                    .map_err(|e| {
+                        AppError::Config(format!(
+                            "Invalid route pattern '{}': {}",
+                            route.pattern, e
+                        ))
+                    })?
+                    .compile_matcher();
+                Ok((matcher, route.clone()))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(Self { routes })
+    }
+
+    /// 匹配规范化后的路径，查询串不参与匹配
+    pub fn find(&self, path: &str) -> Option<&RouteConfig> {
+        let file_path = path.split('?').next().unwrap_or_default();
+        self.routes
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(file_path))
+            .map(|(_, route)| route)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::repositories::redis_repository::RedisRepository;
+use crate::repositories::snapshot_repository::SnapshotRepository;
+use crate::services::cache_key::DECRYPTED_KEY_SUFFIX;
+use crate::services::history_service::content_hash;
+use crate::system::SnapshotConfig;
+use crate::utils::errors::AppResult;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, interval_at};
+
+/// 定期把 Redis 中的过期副本写入磁盘快照，启动时可用快照补齐 Redis
+pub struct SnapshotService {
+    redis_repo: Arc<RedisRepository>,
+    snapshot_repo: Arc<SnapshotRepository>,
+    config: SnapshotConfig,
+    stale_expire_seconds: u64,
+}
+
+impl SnapshotService {
+    pub fn new(
+        redis_repo: Arc<RedisRepository>,
+        snapshot_repo: Arc<SnapshotRepository>,
+        config: SnapshotConfig,
+        stale_expire_seconds: u64,
+    ) -> Self {
+        Self {
+            redis_repo,
+            snapshot_repo,
+            config,
+            stale_expire_seconds,
+        }
+    }
+
+    /// 把快照中有而 Redis 中缺失的条目写回过期副本，返回写回的条目数。
+    /// 只写过期副本，上游可用时请求仍会拉取最新内容
+    pub async fn seed(&self) -> AppResult<usize> {
+        let mut seeded = 0;
+        for entry in self.snapshot_repo.entries() {
+            if self.redis_repo.get_stale(&entry.key).await?.is_some() {
+                continue;
+            }
+            if let Some(content) = self.snapshot_repo.get(&entry.key).await? {
+                self.redis_repo
+                    .set_stale(
+                        &entry.key,
+                        &content,
+                        &content_hash(&content),
+                        self.stale_expire_seconds,
+                    )
+                    .await?;
+                seeded += 1;
+            }
+        }
+        Ok(seeded)
+    }
+
+    /// 写入一次快照，返回快照中的条目数。解密后的条目含有明文密钥，不写入磁盘
+    pub async fn write(&self) -> AppResult<usize> {
+        self.snapshot_repo
+            .discard(|key| key.ends_with(DECRYPTED_KEY_SUFFIX));
+        let mut captured = Vec::new();
+        for key in self.redis_repo.list_stale_keys().await? {
+            if key.ends_with(DECRYPTED_KEY_SUFFIX) {
+                continue;
+            }
+            // 扫描与读取之间过期的条目直接跳过
+            let Some(body) = self.redis_repo.get_stale(&key).await? else {
+                continue;
+            };
+            let content = body.decode()?;
+            captured.push((key, content_hash(&content), content));
+        }
+        self.snapshot_repo
+            .save(captured, self.config.retention_secs)
+            .await
+    }
+
+    /// 启动后台任务，按间隔写入快照
+    pub fn start(self: &Arc<Self>) {
+        let service = self.clone();
+        let period = Duration::from_secs(self.config.interval_secs.max(1));
+
+        tokio::spawn(async move {
+            let mut ticker = interval_at(Instant::now() + period, period);
+            loop {
+                ticker.tick().await;
+                match service.write().await {
+                    Ok(count) => tracing::debug!("Wrote snapshot with {} entries", count),
+                    Err(e) => tracing::warn!("Failed to write snapshot: {}", e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,400 @@
+use super::kv::KvLayout;
+use super::{Upstream, UpstreamChange, yaml_response};
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use url::Url;
+
+// 阻塞查询失败后重试的间隔
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+// 阻塞查询的超时余量，Consul 会在等待时间上额外加最多 1/16 的抖动
+const WATCH_TIMEOUT_MARGIN: Duration = Duration::from_secs(10);
+// 访问令牌请求头
+const TOKEN_HEADER: &str = "x-consul-token";
+// 阻塞查询索引响应头
+const INDEX_HEADER: &str = "x-consul-index";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsulEntry {
+    key: String,
+    value: Option<String>,
+    modify_index: u64,
+}
+
+/// Consul KV 上游，通过阻塞查询监听键前缀的变化
+pub struct ConsulUpstream {
+    client: Client,
+    watch_client: Client,
+    base: Url,
+    layout: KvLayout,
+    token: Option<String>,
+    datacenter: Option<String>,
+    wait_secs: u64,
+    name: String,
+}
+
+impl ConsulUpstream {
+    pub fn new(
+        address: &str,
+        layout: KvLayout,
+        token: Option<String>,
+        datacenter: Option<String>,
+        wait_secs: u64,
+        client: Client,
+        watch_client: Client,
+    ) -> AppResult<Self> {
+        let base = Url::parse(address).map_err(|e| {
+            AppError::Config(format!("Invalid Consul address '{}': {}", address, e))
+        })?;
+        if base.cannot_be_a_base() {
+            return Err(AppError::Config(format!(
+                "Invalid Consul address '{}'",
+                address
+            )));
+        }
+        Ok(Self {
+            client,
+            watch_client,
+            name: format!("consul:{}/{}", base.authority(), layout.prefix()),
+            base,
+            layout,
+            token: token.filter(|token| !token.is_empty()),
+            datacenter,
+            wait_secs: wait_secs.max(1),
+        })
+    }
+
+    /// 构造 `/v1/kv/{key}` 请求，键的每一段单独编码
+    fn request(&self, client: &Client, key: &str) -> RequestBuilder {
+        let mut url = self.base.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments
+                .pop_if_empty()
+                .push("v1")
+                .push("kv")
+                .extend(key.split('/'));
+        }
+        if let Some(datacenter) = &self.datacenter {
+            url.query_pairs_mut().append_pair("dc", datacenter);
+        }
+
+        let mut request = client.get(url);
+        if let Some(token) = &self.token {
+            request = request.header(TOKEN_HEADER, token);
+        }
+        request
+    }
+
+    /// 读取前缀下的所有键，返回阻塞查询索引与键列表，前缀不存在时返回空列表
+    async fn list(&self, request: RequestBuilder) -> AppResult<(Option<u64>, Vec<ConsulEntry>)> {
+        let response = request.send().await.map_err(AppError::HttpClient)?;
+        let index = response
+            .headers()
+            .get(INDEX_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok((index, Vec::new())),
+            status if status.is_success() => {
+                let entries = response.json().await.map_err(AppError::HttpClient)?;
+                Ok((index, entries))
+            }
+            status => Err(AppError::Proxy(format!(
+                "Consul returned status {} for {}",
+                status,
+                response.url()
+            ))),
+        }
+    }
+
+    /// 执行一次阻塞查询，返回新的索引与各键的修改索引
+    async fn poll(&self, index: u64) -> AppResult<(u64, HashMap<String, u64>)> {
+        let wait = Duration::from_secs(self.wait_secs);
+        let request = self
+            .request(&self.watch_client, self.layout.prefix())
+            .query(&[("recurse", "true")])
+            .query(&[("index", index.to_string())])
+            .query(&[("wait", format!("{}s", self.wait_secs))])
+            .timeout(wait + wait / 16 + WATCH_TIMEOUT_MARGIN);
+        let (next_index, entries) = self.list(request).await?;
+        let next_index = next_index
+            .ok_or_else(|| AppError::Proxy("Consul response has no X-Consul-Index".to_string()))?;
+        let entries = entries
+            .into_iter()
+            .map(|entry| (entry.key, entry.modify_index))
+            .collect();
+        Ok((next_index, entries))
+    }
+}
+
+#[async_trait]
+impl Upstream for ConsulUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        self.layout
+            .key(normalized)
+            .map(|key| format!("{}/v1/kv/{}", self.base.as_str().trim_end_matches('/'), key))
+    }
+
+    async fn fetch(&self, normalized: &str, _headers: &HeaderMap) -> AppResult<Response> {
+        let key = self.layout.key(normalized)?;
+        let response = self
+            .request(&self.client, &key)
+            .query(&[("raw", "true")])
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+        match response.status() {
+            StatusCode::NOT_FOUND => {}
+            status if status.is_success() => {
+                let body = response.bytes().await.map_err(AppError::HttpClient)?;
+                return Ok(yaml_response(Some(body)));
+            }
+            status => {
+                return Err(AppError::Proxy(format!(
+                    "Consul returned status {} for key {}",
+                    status, key
+                )));
+            }
+        }
+
+        // 没有同名的键时读取键树
+        let tree_prefix = self.layout.tree_prefix(normalized)?;
+        let request = self
+            .request(&self.client, &tree_prefix)
+            .query(&[("recurse", "true")]);
+        let (_, entries) = self.list(request).await?;
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let value = match entry.value {
+                    Some(value) => STANDARD.decode(value).map_err(|e| {
+                        AppError::Proxy(format!("Invalid Consul value for {}: {}", entry.key, e))
+                    })?,
+                    None => Vec::new(),
+                };
+                Ok((entry.key, Bytes::from(value)))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(yaml_response(
+            self.layout.render_tree(&tree_prefix, entries)?,
+        ))
+    }
+
+    fn watch(self: Arc<Self>, changes: UnboundedSender<UpstreamChange>) {
+        tokio::spawn(async move {
+            let mut index = 0;
+            let mut known: Option<HashMap<String, u64>> = None;
+            loop {
+                let (next_index, entries) = match self.poll(index).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        tracing::warn!("Consul watch on {} failed: {}", self.name, e);
+                        tokio::time::sleep(WATCH_RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                // 索引回退说明 Consul 的数据被重置，需要从头开始
+                index = if next_index < index {
+                    0
+                } else {
+                    next_index.max(1)
+                };
+
+                if let Some(previous) = &known {
+                    let mut changed: Vec<String> = entries
+                        .iter()
+                        .filter(|(key, modify_index)| previous.get(*key) != Some(*modify_index))
+                        .map(|(key, _)| key.clone())
+                        .collect();
+                    changed.extend(
+                        previous
+                            .keys()
+                            .filter(|key| !entries.contains_key(*key))
+                            .cloned(),
+                    );
+                    if !changed.is_empty() {
+                        let change = UpstreamChange {
+                            upstream: self.name.clone(),
+                            keys: changed,
+                        };
+                        if changes.send(change).is_err() {
+                            return;
+                        }
+                    }
+                }
+                known = Some(entries);
+            }
+        });
+    }
+
+    fn affects(&self, normalized: &str, changed: &str) -> bool {
+        self.layout.affects(normalized, changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::upstream::serve_fake;
+    use axum::{
+        Json, Router,
+        extract::{Path, Query, State},
+        response::{IntoResponse, Response as AxumResponse},
+        routing::get,
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc;
+
+    /// 模拟 Consul KV：读取按键或前缀返回数据，阻塞查询依次返回给定的修改索引快照，
+    /// 快照用完后一直等待
+    #[derive(Clone)]
+    struct FakeConsul {
+        kvs: Arc<Vec<(String, String)>>,
+        snapshots: Arc<Vec<Vec<(String, u64)>>>,
+        polls: Arc<AtomicUsize>,
+    }
+
+    async fn kv(
+        State(fake): State<FakeConsul>,
+        Path(key): Path<String>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> AxumResponse {
+        if query.contains_key("index") {
+            let poll = fake.polls.fetch_add(1, Ordering::Relaxed);
+            let Some(snapshot) = fake.snapshots.get(poll) else {
+                std::future::pending::<()>().await;
+                unreachable!();
+            };
+            let entries: Vec<_> = snapshot
+                .iter()
+                .map(|(key, index)| json!({ "Key": key, "Value": null, "ModifyIndex": index }))
+                .collect();
+            return ([(INDEX_HEADER, (poll + 1).to_string())], Json(entries)).into_response();
+        }
+        if query.contains_key("raw") {
+            return match fake.kvs.iter().find(|(candidate, _)| *candidate == key) {
+                Some((_, value)) => value.clone().into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            };
+        }
+        let entries: Vec<_> = fake
+            .kvs
+            .iter()
+            .filter(|(candidate, _)| candidate.starts_with(&key))
+            .map(|(key, value)| {
+                json!({ "Key": key, "Value": STANDARD.encode(value), "ModifyIndex": 1 })
+            })
+            .collect();
+        if entries.is_empty() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        Json(entries).into_response()
+    }
+
+    async fn fake_consul(
+        kvs: &[(&str, &str)],
+        snapshots: &[&[(&str, u64)]],
+    ) -> Arc<ConsulUpstream> {
+        let fake = FakeConsul {
+            kvs: Arc::new(
+                kvs.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            snapshots: Arc::new(
+                snapshots
+                    .iter()
+                    .map(|snapshot| {
+                        snapshot
+                            .iter()
+                            .map(|(key, index)| (key.to_string(), *index))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            polls: Arc::new(AtomicUsize::new(0)),
+        };
+        let router = Router::new()
+            .route("/v1/kv/{*key}", get(kv))
+            .with_state(fake);
+        let address = serve_fake(router).await;
+        Arc::new(
+            ConsulUpstream::new(
+                &address,
+                KvLayout::new("/kv/**", "config/"),
+                None,
+                None,
+                1,
+                Client::new(),
+                Client::new(),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn fetches_key_or_renders_tree() {
+        let consul = fake_consul(
+            &[
+                ("config/app.yml", "name: app"),
+                ("config/db/url", "jdbc:mysql://db"),
+                ("config/db/pool", "10"),
+            ],
+            &[],
+        )
+        .await;
+        let headers = HeaderMap::new();
+
+        let response = consul.fetch("/kv/app.yml", &headers).await.unwrap();
+        assert_eq!(response.bytes().await.unwrap(), "name: app");
+
+        let response = consul.fetch("/kv/db.yml", &headers).await.unwrap();
+        let value: serde_yml::Value =
+            serde_yml::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(value["url"], serde_yml::Value::from("jdbc:mysql://db"));
+        assert_eq!(value["pool"], serde_yml::Value::from(10));
+
+        let response = consul.fetch("/kv/missing.yml", &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn sends_modified_and_deleted_keys() {
+        let consul = fake_consul(
+            &[],
+            &[
+                &[("config/app.yml", 1), ("config/db/url", 1)],
+                &[("config/app.yml", 2), ("config/other.yml", 1)],
+            ],
+        )
+        .await;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        consul.clone().watch(sender);
+
+        let change = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut keys = change.keys;
+        keys.sort();
+        assert_eq!(
+            keys,
+            ["config/app.yml", "config/db/url", "config/other.yml"]
+        );
+        assert!(consul.affects("/kv/db.yml", "config/db/url"));
+    }
+}
@@ -0,0 +1,60 @@
+use super::spring::{EXTENSIONS, SpringPath};
+use super::{Upstream, yaml_response};
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use reqwest::Response;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// 本地目录上游，与 Spring 的 native 模式一样忽略请求路径中的标签
+pub struct DirectoryUpstream {
+    root: PathBuf,
+    name: String,
+}
+
+impl DirectoryUpstream {
+    pub fn new(path: &str) -> AppResult<Self> {
+        let root = PathBuf::from(path);
+        if !root.is_dir() {
+            return Err(AppError::Config(format!(
+                "Upstream directory '{}' does not exist",
+                path
+            )));
+        }
+        Ok(Self {
+            root,
+            name: format!("directory:{}", path),
+        })
+    }
+}
+
+#[async_trait]
+impl Upstream for DirectoryUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        SpringPath::parse(normalized)?;
+        Ok(format!("{}{}", self.name, normalized))
+    }
+
+    async fn fetch(&self, normalized: &str, _headers: &HeaderMap) -> AppResult<Response> {
+        let spring = SpringPath::parse(normalized)?;
+        let mut sources = Vec::new();
+        for name in spring.candidates() {
+            for ext in EXTENSIONS {
+                let file = format!("{}.{}", name, ext);
+                match fs::read(self.root.join(&file)).await {
+                    Ok(content) => sources.push((file, Bytes::from(content))),
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(AppError::Io(e)),
+                }
+            }
+        }
+        Ok(yaml_response(spring.merge(&sources)?))
+    }
+}
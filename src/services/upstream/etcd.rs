@@ -0,0 +1,537 @@
+use super::kv::KvLayout;
+use super::{Upstream, UpstreamChange, yaml_response};
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use reqwest::{Client, Response};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use url::Url;
+
+// 监听中断后重新连接的间隔
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// gRPC 网关把 int64 编码为字符串
+#[derive(Deserialize, Default)]
+struct ResponseHeader {
+    #[serde(default)]
+    revision: String,
+}
+
+#[derive(Deserialize)]
+struct RangeResponse {
+    #[serde(default)]
+    header: ResponseHeader,
+    #[serde(default)]
+    kvs: Vec<KeyValue>,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+    key: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    mod_revision: String,
+}
+
+#[derive(Deserialize)]
+struct WatchMessage {
+    result: Option<WatchResult>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct WatchResult {
+    #[serde(default)]
+    header: ResponseHeader,
+    #[serde(default)]
+    created: bool,
+    #[serde(default)]
+    events: Vec<WatchEvent>,
+    #[serde(default)]
+    canceled: bool,
+    #[serde(default)]
+    compact_revision: String,
+    #[serde(default)]
+    cancel_reason: String,
+}
+
+#[derive(Deserialize)]
+struct WatchEvent {
+    kv: KeyValue,
+}
+
+/// etcd v3 上游，通过 gRPC 网关的 JSON 接口读取键值，并用 watch 监听键前缀的变化
+pub struct EtcdUpstream {
+    client: Client,
+    watch_client: Client,
+    base: Url,
+    layout: KvLayout,
+    name: String,
+    progress_timeout: Duration,
+}
+
+impl EtcdUpstream {
+    pub fn new(
+        address: &str,
+        layout: KvLayout,
+        progress_timeout_secs: u64,
+        client: Client,
+        watch_client: Client,
+    ) -> AppResult<Self> {
+        let base = Url::parse(address)
+            .map_err(|e| AppError::Config(format!("Invalid etcd address '{}': {}", address, e)))?;
+        if base.cannot_be_a_base() {
+            return Err(AppError::Config(format!(
+                "Invalid etcd address '{}'",
+                address
+            )));
+        }
+        Ok(Self {
+            client,
+            watch_client,
+            name: format!("etcd:{}/{}", base.authority(), layout.prefix()),
+            base,
+            layout,
+            progress_timeout: Duration::from_secs(progress_timeout_secs.max(1)),
+        })
+    }
+
+    fn endpoint(&self, path: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().extend(path);
+        }
+        url
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &[&str],
+        body: serde_json::Value,
+    ) -> AppResult<T> {
+        let url = self.endpoint(path);
+        let response = self
+            .client
+            .post(url.clone())
+            .json(&body)
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+        if !response.status().is_success() {
+            return Err(AppError::Proxy(format!(
+                "etcd returned status {} for {}",
+                response.status(),
+                url
+            )));
+        }
+        response.json().await.map_err(AppError::HttpClient)
+    }
+
+    /// 读取单个键，或传入 range_end 时读取 [key, range_end) 区间
+    async fn range(&self, key: &str, range_end: Option<Vec<u8>>) -> AppResult<RangeResponse> {
+        let mut body = json!({ "key": STANDARD.encode(range_key(key)) });
+        if let Some(range_end) = range_end {
+            body["range_end"] = json!(STANDARD.encode(range_end));
+        }
+        self.post(&["v3", "kv", "range"], body).await
+    }
+
+    /// 建立一次监听，把变化的键发送到 `changes`，`revision` 随事件推进，
+    /// 重新连接时从中断处继续
+    async fn watch_once(
+        &self,
+        revision: &mut u64,
+        changes: &UnboundedSender<UpstreamChange>,
+    ) -> AppResult<()> {
+        let prefix = self.layout.prefix();
+        if *revision == 0 {
+            let current = self.range(prefix, Some(prefix_end(prefix))).await?;
+            *revision = parse_revision(&current.header.revision) + 1;
+        }
+
+        let body = json!({
+            "create_request": {
+                "key": STANDARD.encode(range_key(prefix)),
+                "range_end": STANDARD.encode(prefix_end(prefix)),
+                "start_revision": revision.to_string(),
+                "progress_notify": true,
+            }
+        });
+        let url = self.endpoint(&["v3", "watch"]);
+        let response = self
+            .watch_client
+            .post(url.clone())
+            .json(&body)
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+        if !response.status().is_success() {
+            return Err(AppError::Proxy(format!(
+                "etcd returned status {} for {}",
+                response.status(),
+                url
+            )));
+        }
+
+        // 网关以换行分隔的 JSON 流返回监听结果。开启了进度通知，
+        // 连接空闲超过 progress_timeout 说明连接已失效，重新建立监听
+        let mut stream = response.bytes_stream();
+        let mut buffer = Vec::new();
+        loop {
+            let chunk = match tokio::time::timeout(self.progress_timeout, stream.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => return Ok(()),
+                Err(_) => {
+                    return Err(AppError::Proxy(format!(
+                        "etcd watch on {} received no progress for {:?}",
+                        self.name, self.progress_timeout
+                    )));
+                }
+            };
+            buffer.extend_from_slice(&chunk.map_err(AppError::HttpClient)?);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                let message: WatchMessage = serde_json::from_slice(&line)?;
+                if let Some(error) = message.error {
+                    return Err(AppError::Proxy(format!("etcd watch error: {}", error)));
+                }
+                let Some(result) = message.result else {
+                    continue;
+                };
+
+                if !result.compact_revision.is_empty() && result.compact_revision != "0" {
+                    // 需要的版本已被压缩，无法得知期间的变化，视为全部变化
+                    tracing::warn!(
+                        "etcd watch on {} fell behind compaction at revision {}",
+                        self.name,
+                        result.compact_revision
+                    );
+                    *revision = 0;
+                    let change = UpstreamChange {
+                        upstream: self.name.clone(),
+                        keys: Vec::new(),
+                    };
+                    let _ = changes.send(change);
+                    return Ok(());
+                }
+                if result.canceled {
+                    return Err(AppError::Proxy(format!(
+                        "etcd watch canceled: {}",
+                        result.cancel_reason
+                    )));
+                }
+                if result.events.is_empty() {
+                    // 进度通知表示该版本之前的事件都已送达，重新连接时从其后继续
+                    if !result.created {
+                        *revision = (*revision).max(parse_revision(&result.header.revision) + 1);
+                    }
+                    continue;
+                }
+
+                let mut keys = Vec::with_capacity(result.events.len());
+                for event in result.events {
+                    *revision = (*revision).max(parse_revision(&event.kv.mod_revision) + 1);
+                    keys.push(decode_key(&event.kv.key)?);
+                }
+                let change = UpstreamChange {
+                    upstream: self.name.clone(),
+                    keys,
+                };
+                if changes.send(change).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Upstream for EtcdUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        self.layout
+            .key(normalized)
+            .map(|key| format!("{}/{}", self.name, key))
+    }
+
+    async fn fetch(&self, normalized: &str, _headers: &HeaderMap) -> AppResult<Response> {
+        let key = self.layout.key(normalized)?;
+        if let Some(kv) = self.range(&key, None).await?.kvs.into_iter().next() {
+            let value = STANDARD
+                .decode(kv.value)
+                .map_err(|e| AppError::Proxy(format!("Invalid etcd value for {}: {}", key, e)))?;
+            return Ok(yaml_response(Some(Bytes::from(value))));
+        }
+
+        // 没有同名的键时读取键树
+        let tree_prefix = self.layout.tree_prefix(normalized)?;
+        let entries = self
+            .range(&tree_prefix, Some(prefix_end(&tree_prefix)))
+            .await?
+            .kvs
+            .into_iter()
+            .map(|kv| {
+                let key = decode_key(&kv.key)?;
+                let value = STANDARD.decode(kv.value).map_err(|e| {
+                    AppError::Proxy(format!("Invalid etcd value for {}: {}", key, e))
+                })?;
+                Ok((key, Bytes::from(value)))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(yaml_response(
+            self.layout.render_tree(&tree_prefix, entries)?,
+        ))
+    }
+
+    fn watch(self: Arc<Self>, changes: UnboundedSender<UpstreamChange>) {
+        tokio::spawn(async move {
+            let mut revision = 0;
+            while !changes.is_closed() {
+                if let Err(e) = self.watch_once(&mut revision, &changes).await {
+                    tracing::warn!("etcd watch on {} failed: {}", self.name, e);
+                }
+                tokio::time::sleep(WATCH_RETRY_DELAY).await;
+            }
+        });
+    }
+
+    fn affects(&self, normalized: &str, changed: &str) -> bool {
+        self.layout.affects(normalized, changed)
+    }
+}
+
+/// 空前缀表示整个键空间，etcd 约定用 `\0` 作为起始键
+fn range_key(key: &str) -> Vec<u8> {
+    if key.is_empty() {
+        vec![0]
+    } else {
+        key.as_bytes().to_vec()
+    }
+}
+
+/// 前缀区间的结束键：最后一个不为 0xff 的字节加一，空前缀时为 `\0`（直到键空间末尾）
+fn prefix_end(prefix: &str) -> Vec<u8> {
+    let mut end = prefix.as_bytes().to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    vec![0]
+}
+
+fn parse_revision(revision: &str) -> u64 {
+    revision.parse().unwrap_or(0)
+}
+
+fn decode_key(key: &str) -> AppResult<String> {
+    STANDARD
+        .decode(key)
+        .map(|key| String::from_utf8_lossy(&key).into_owned())
+        .map_err(|e| AppError::Proxy(format!("Invalid etcd key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::upstream::serve_fake;
+    use axum::{Json, Router, body::Body, extract::State, http::StatusCode, routing::post};
+    use std::collections::BTreeMap;
+    use tokio::sync::mpsc;
+
+    /// 模拟 gRPC 网关：range 按键区间返回数据，watch 返回给定的消息后结束，
+    /// 没有消息时保持连接不发送任何内容
+    #[derive(Clone)]
+    struct FakeEtcd {
+        kvs: Arc<BTreeMap<String, String>>,
+        watch_lines: Arc<Vec<String>>,
+    }
+
+    async fn range(
+        State(fake): State<FakeEtcd>,
+        Json(request): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        let decode = |field: &str| {
+            request[field]
+                .as_str()
+                .map(|value| STANDARD.decode(value).unwrap())
+        };
+        let key = decode("key").unwrap();
+        let range_end = decode("range_end");
+        let kvs: Vec<_> = fake
+            .kvs
+            .iter()
+            .filter(|(candidate, _)| match &range_end {
+                Some(end) => {
+                    candidate.as_bytes() >= key.as_slice()
+                        && (end.as_slice() == [0] || candidate.as_bytes() < end.as_slice())
+                }
+                None => candidate.as_bytes() == key.as_slice(),
+            })
+            .map(|(key, value)| {
+                json!({
+                    "key": STANDARD.encode(key),
+                    "value": STANDARD.encode(value),
+                    "mod_revision": "3",
+                })
+            })
+            .collect();
+        Json(json!({ "header": { "revision": "7" }, "kvs": kvs }))
+    }
+
+    async fn watch(State(fake): State<FakeEtcd>) -> Body {
+        if fake.watch_lines.is_empty() {
+            return Body::from_stream(
+                futures_util::stream::pending::<Result<Bytes, std::io::Error>>(),
+            );
+        }
+        let body: String = fake
+            .watch_lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        Body::from(body)
+    }
+
+    async fn fake_etcd(kvs: &[(&str, &str)], watch_lines: &[serde_json::Value]) -> EtcdUpstream {
+        let fake = FakeEtcd {
+            kvs: Arc::new(
+                kvs.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            watch_lines: Arc::new(watch_lines.iter().map(|line| line.to_string()).collect()),
+        };
+        let router = Router::new()
+            .route("/v3/kv/range", post(range))
+            .route("/v3/watch", post(watch))
+            .with_state(fake);
+        let address = serve_fake(router).await;
+        EtcdUpstream::new(
+            &address,
+            KvLayout::new("/etcd/**", "config/"),
+            1,
+            Client::new(),
+            Client::new(),
+        )
+        .unwrap()
+    }
+
+    fn encoded(key: &str) -> String {
+        STANDARD.encode(key)
+    }
+
+    #[test]
+    fn computes_prefix_end() {
+        assert_eq!(prefix_end("config/"), b"config0");
+        assert_eq!(prefix_end("a"), b"b");
+        // U+00FF 编码为 0xc3 0xbf，最后一个字节加一
+        assert_eq!(prefix_end("\u{ff}"), [0xc3, 0xc0]);
+        assert_eq!(prefix_end(""), [0]);
+        assert_eq!(range_key(""), [0]);
+    }
+
+    #[tokio::test]
+    async fn fetches_key_or_renders_tree() {
+        let etcd = fake_etcd(
+            &[
+                ("config/app.yml", "name: app"),
+                ("config/db/url", "jdbc:mysql://db"),
+                ("config/db/pool", "10"),
+            ],
+            &[],
+        )
+        .await;
+        let headers = HeaderMap::new();
+
+        let response = etcd.fetch("/etcd/app.yml", &headers).await.unwrap();
+        assert_eq!(response.bytes().await.unwrap(), "name: app");
+
+        let response = etcd.fetch("/etcd/db.yml", &headers).await.unwrap();
+        let value: serde_yml::Value =
+            serde_yml::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(value["url"], serde_yml::Value::from("jdbc:mysql://db"));
+        assert_eq!(value["pool"], serde_yml::Value::from(10));
+
+        let response = etcd.fetch("/etcd/missing.yml", &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn sends_changed_keys_and_follows_progress() {
+        let etcd = fake_etcd(
+            &[],
+            &[
+                json!({ "result": { "header": { "revision": "10" }, "created": true } }),
+                json!({ "result": { "header": { "revision": "11" }, "events": [
+                    { "kv": { "key": encoded("config/app.yml"), "mod_revision": "11" } }
+                ] } }),
+                json!({ "result": { "header": { "revision": "15" } } }),
+            ],
+        )
+        .await;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let mut revision = 6;
+        etcd.watch_once(&mut revision, &sender).await.unwrap();
+        let change = receiver.try_recv().unwrap();
+        assert_eq!(change.keys, ["config/app.yml"]);
+        assert!(etcd.affects("/etcd/app.yml", &change.keys[0]));
+        assert_eq!(revision, 16);
+    }
+
+    #[tokio::test]
+    async fn creation_does_not_skip_pending_events() {
+        let etcd = fake_etcd(
+            &[],
+            &[json!({ "result": { "header": { "revision": "10" }, "created": true } })],
+        )
+        .await;
+        let (sender, _receiver) = mpsc::unbounded_channel();
+
+        let mut revision = 6;
+        etcd.watch_once(&mut revision, &sender).await.unwrap();
+        assert_eq!(revision, 6);
+    }
+
+    #[tokio::test]
+    async fn compaction_invalidates_everything() {
+        let etcd = fake_etcd(
+            &[],
+            &[json!({ "result": { "compact_revision": "8", "canceled": true } })],
+        )
+        .await;
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let mut revision = 6;
+        etcd.watch_once(&mut revision, &sender).await.unwrap();
+        assert!(receiver.try_recv().unwrap().keys.is_empty());
+        assert_eq!(revision, 0);
+    }
+
+    #[tokio::test]
+    async fn times_out_without_progress() {
+        let etcd = fake_etcd(&[], &[]).await;
+        let (sender, _receiver) = mpsc::unbounded_channel();
+
+        let mut revision = 6;
+        let result = etcd.watch_once(&mut revision, &sender).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use super::spring::{EXTENSIONS, SpringPath};
+use super::{Upstream, yaml_response};
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use git2::{ErrorCode, Object, ObjectType, Reference, Repository};
+use reqwest::Response;
+use std::path::{Path, PathBuf};
+
+/// 本地 Git 仓库上游。每次请求重新打开仓库，外部的 fetch 或提交无需重启即可生效；
+/// 标签可以是分支、标签或提交哈希，本地分支不存在时尝试 origin 下的远程分支
+pub struct GitUpstream {
+    repository: PathBuf,
+    default_label: String,
+    name: String,
+}
+
+impl GitUpstream {
+    pub fn new(repository: &str, default_label: &str) -> AppResult<Self> {
+        Repository::open(repository).map_err(|e| {
+            AppError::Config(format!(
+                "Failed to open Git repository '{}': {}",
+                repository, e
+            ))
+        })?;
+        validate_label(default_label).map_err(|_| {
+            AppError::Config(format!("Invalid Git default label '{}'", default_label))
+        })?;
+        Ok(Self {
+            repository: PathBuf::from(repository),
+            default_label: default_label.to_string(),
+            name: format!("git:{}", repository),
+        })
+    }
+}
+
+#[async_trait]
+impl Upstream for GitUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        if let Some(label) = SpringPath::parse(normalized)?.label {
+            validate_label(&label)?;
+        }
+        Ok(format!("{}{}", self.name, normalized))
+    }
+
+    async fn fetch(&self, normalized: &str, _headers: &HeaderMap) -> AppResult<Response> {
+        let spring = SpringPath::parse(normalized)?;
+        let repository = self.repository.clone();
+        let label = spring
+            .label
+            .clone()
+            .unwrap_or_else(|| self.default_label.clone());
+        validate_label(&label)?;
+        let candidates = spring.candidates();
+
+        // libgit2 是阻塞调用，放到专用线程执行
+        let sources =
+            tokio::task::spawn_blocking(move || read_sources(&repository, &label, &candidates))
+                .await
+                .map_err(|e| AppError::Internal(format!("Git read task failed: {}", e)))??;
+        match sources {
+            Some(sources) => Ok(yaml_response(spring.merge(&sources)?)),
+            None => Ok(yaml_response(None)),
+        }
+    }
+}
+
+/// 读取标签下存在的候选文件，标签不存在时返回 None
+fn read_sources(
+    repository: &Path,
+    label: &str,
+    candidates: &[String],
+) -> AppResult<Option<Vec<(String, Bytes)>>> {
+    let repo = Repository::open(repository).map_err(git_error)?;
+    let Some(revision) = resolve_label(&repo, label)? else {
+        return Ok(None);
+    };
+    let tree = revision.peel_to_tree().map_err(git_error)?;
+
+    let mut sources = Vec::new();
+    for name in candidates {
+        for ext in EXTENSIONS {
+            let file = format!("{}.{}", name, ext);
+            let entry = match tree.get_path(Path::new(&file)) {
+                Ok(entry) => entry,
+                Err(e) if e.code() == ErrorCode::NotFound => continue,
+                Err(e) => return Err(git_error(e)),
+            };
+            let blob = entry
+                .to_object(&repo)
+                .and_then(|object| object.peel_to_blob())
+                .map_err(git_error)?;
+            sources.push((file, Bytes::copy_from_slice(blob.content())));
+        }
+    }
+    Ok(Some(sources))
+}
+
+/// 标签只能是分支名、标签名或提交哈希，拒绝 `main:subdir`、`HEAD~1` 等 revspec 语法
+fn validate_label(label: &str) -> AppResult<()> {
+    if Reference::is_valid_name(&format!("refs/heads/{}", label)) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid Git label: {}",
+            label
+        )))
+    }
+}
+
+/// 依次按本地分支、标签、origin 下的远程分支与提交哈希解析标签
+fn resolve_label<'r>(repo: &'r Repository, label: &str) -> AppResult<Option<Object<'r>>> {
+    for name in [
+        format!("refs/heads/{}", label),
+        format!("refs/tags/{}", label),
+        format!("refs/remotes/origin/{}", label),
+    ] {
+        match repo.find_reference(&name) {
+            Ok(reference) => {
+                return reference
+                    .peel(ObjectType::Commit)
+                    .map(Some)
+                    .map_err(git_error);
+            }
+            Err(e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(git_error(e)),
+        }
+    }
+
+    // 十六进制的标签才按（可缩写的）提交哈希解析，不会触发 revspec 语法
+    if label.len() < 4 || label.len() > 40 || !label.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    match repo
+        .revparse_single(label)
+        .and_then(|object| object.peel(ObjectType::Commit))
+    {
+        Ok(object) => Ok(Some(object)),
+        Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => Ok(None),
+        Err(e) if e.code() == ErrorCode::Ambiguous => Err(AppError::BadRequest(format!(
+            "Ambiguous Git commit: {}",
+            label
+        ))),
+        Err(e) => Err(git_error(e)),
+    }
+}
+
+fn git_error(e: git2::Error) -> AppError {
+    AppError::Proxy(format!("Git repository error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+
+    /// 在临时目录创建仓库：main 上两次提交，v1 标签指向第一次提交
+    fn repository(name: &str) -> (PathBuf, Oid, Oid) {
+        let path = std::env::temp_dir().join(format!(
+            "config-cache-proxy-git-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let commit = |content: &str, parents: &[Oid]| {
+            let blob = repo.blob(content.as_bytes()).unwrap();
+            let mut tree = repo.treebuilder(None).unwrap();
+            tree.insert("app.yml", blob, 0o100644).unwrap();
+            let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+            let parents: Vec<_> = parents
+                .iter()
+                .map(|id| repo.find_commit(*id).unwrap())
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(
+                Some("refs/heads/main"),
+                &signature,
+                &signature,
+                content,
+                &tree,
+                &parents,
+            )
+            .unwrap()
+        };
+        let first = commit("version: 1", &[]);
+        let second = commit("version: 2", &[first]);
+        repo.reference("refs/tags/v1", first, false, "tag").unwrap();
+        (path, first, second)
+    }
+
+    fn version(repository: &Path, label: &str) -> Option<String> {
+        read_sources(repository, label, &["app".to_string()])
+            .unwrap()
+            .map(|sources| String::from_utf8(sources[0].1.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn resolves_branches_tags_and_commits() {
+        let (path, first, second) = repository("labels");
+        assert_eq!(version(&path, "main").as_deref(), Some("version: 2"));
+        assert_eq!(version(&path, "v1").as_deref(), Some("version: 1"));
+        assert_eq!(
+            version(&path, &first.to_string()[..8]).as_deref(),
+            Some("version: 1")
+        );
+        assert_eq!(
+            version(&path, &second.to_string()).as_deref(),
+            Some("version: 2")
+        );
+        assert_eq!(version(&path, "missing"), None);
+        assert_eq!(version(&path, "HEAD"), None);
+    }
+
+    #[test]
+    fn rejects_revspec_labels() {
+        let (path, _, _) = repository("revspecs");
+        let upstream = GitUpstream::new(path.to_str().unwrap(), "main").unwrap();
+        for label in [
+            "main:app.yml",
+            "main~1",
+            "main^",
+            "v1^{tree}",
+            "@{1}",
+            "main..v1",
+        ] {
+            assert!(validate_label(label).is_err(), "{}", label);
+            let path = format!("/{}/app-default.yml", urlencoding::encode(label));
+            assert!(
+                matches!(upstream.locate(&path), Err(AppError::BadRequest(_))),
+                "{}",
+                label
+            );
+        }
+        assert!(upstream.locate("/feature(_)x/app-default.yml").is_ok());
+        assert!(GitUpstream::new(path.to_str().unwrap(), "main~1").is_err());
+    }
+}
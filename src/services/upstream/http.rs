@@ -0,0 +1,90 @@
+use super::Upstream;
+use crate::services::cache_key::parse_header_names;
+use crate::services::upstream_url::UpstreamUrlResolver;
+use crate::system::{RetryConfig, SystemConfig};
+use crate::utils::backoff::backoff_delay;
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::{HeaderMap, HeaderName, StatusCode};
+use reqwest::{Client, Response};
+
+/// Spring 配置中心等 HTTP 上游，转发指定的请求头并按退避策略重试
+pub struct HttpUpstream {
+    client: Client,
+    resolver: UpstreamUrlResolver,
+    name: String,
+    forward_headers: Vec<HeaderName>,
+    retry: RetryConfig,
+}
+
+impl HttpUpstream {
+    pub fn new(base_url: &str, client: Client, config: &SystemConfig) -> AppResult<Self> {
+        let resolver = UpstreamUrlResolver::new(base_url)?;
+        Ok(Self {
+            client,
+            name: resolver.base().authority().to_string(),
+            resolver,
+            forward_headers: parse_header_names(&config.headers.forward)?,
+            retry: config.http_client.retry.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Upstream for HttpUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        self.resolver.resolve(normalized).map(|url| url.to_string())
+    }
+
+    /// 请求上游，对连接错误、超时和网关类错误按指数退避加抖动重试
+    async fn fetch(&self, normalized: &str, headers: &HeaderMap) -> AppResult<Response> {
+        let url = self.resolver.resolve(normalized)?;
+        let mut attempt = 0;
+        loop {
+            // 附带需要转发的请求头
+            let mut request = self.client.get(url.clone());
+            for name in &self.forward_headers {
+                for value in headers.get_all(name) {
+                    request = request.header(name, value);
+                }
+            }
+
+            let retryable = match request.send().await {
+                Ok(response) if !is_retryable_status(response.status()) => return Ok(response),
+                Ok(response) if attempt >= self.retry.max_retries => return Ok(response),
+                Ok(response) => format!("status {}", response.status()),
+                Err(e) if attempt >= self.retry.max_retries || !is_retryable_error(&e) => {
+                    return Err(AppError::HttpClient(e));
+                }
+                Err(e) => e.to_string(),
+            };
+
+            let delay = backoff_delay(&self.retry, attempt);
+            attempt += 1;
+            tracing::warn!(
+                "Upstream request to {} failed ({}), retry {}/{} in {:?}",
+                url,
+                retryable,
+                attempt,
+                self.retry.max_retries,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
@@ -0,0 +1,208 @@
+use crate::services::format_converter;
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use serde_yml::{Mapping, Value};
+
+/// 键值存储的路径映射：请求路径去掉路由前缀后为 `{path}.yml`，先查找键 `{prefix}{path}.yml`，
+/// 不存在时把 `{prefix}{path}/` 下的所有键按 `/` 展开为 YAML 树
+pub struct KvLayout {
+    route_prefix: String,
+    prefix: String,
+}
+
+impl KvLayout {
+    /// 路由前缀取路由模式中第一个通配符之前的目录部分，如 `/kv/**` 的 `/kv/`
+    pub fn new(route_pattern: &str, prefix: &str) -> Self {
+        let prefix = prefix.trim_start_matches('/');
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+        let literal = route_pattern
+            .split(['*', '?', '[', '{', '\\'])
+            .next()
+            .unwrap_or_default();
+        let route_prefix = literal
+            .rsplit_once('/')
+            .map(|(dir, _)| format!("{}/", dir))
+            .unwrap_or_default();
+        Self {
+            route_prefix,
+            prefix,
+        }
+    }
+
+    /// 需要监听的键前缀
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// 与请求路径同名的键
+    pub fn key(&self, normalized: &str) -> AppResult<String> {
+        Ok(format!(
+            "{}{}",
+            self.prefix,
+            self.relative_path(normalized)?
+        ))
+    }
+
+    /// 请求路径去掉扩展名后对应的键树前缀
+    pub fn tree_prefix(&self, normalized: &str) -> AppResult<String> {
+        let path = self.relative_path(normalized)?;
+        let stem = path.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&path);
+        Ok(format!("{}{}/", self.prefix, stem))
+    }
+
+    /// 判断变化的键是否影响请求路径
+    pub fn affects(&self, normalized: &str, changed: &str) -> bool {
+        self.key(normalized).is_ok_and(|key| key == changed)
+            || self
+                .tree_prefix(normalized)
+                .is_ok_and(|prefix| changed.starts_with(&prefix))
+    }
+
+    /// 把键树渲染为 YAML，值能解析为 YAML 时保留其类型，否则作为字符串。
+    /// 没有任何键时返回 None
+    pub fn render_tree(
+        &self,
+        tree_prefix: &str,
+        entries: Vec<(String, Bytes)>,
+    ) -> AppResult<Option<Bytes>> {
+        let mut root = Value::Mapping(Mapping::new());
+        let mut found = false;
+        for (key, value) in entries {
+            let Some(relative) = key.strip_prefix(tree_prefix) else {
+                continue;
+            };
+            // 以 `/` 结尾的是 Consul 的目录占位键
+            if relative.is_empty() || relative.ends_with('/') {
+                continue;
+            }
+            let value = serde_yml::from_slice::<Value>(&value)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&value).into_owned()));
+            insert(&mut root, relative.split('/'), value);
+            found = true;
+        }
+        if !found {
+            return Ok(None);
+        }
+        format_converter::render(&root, ConfigFormat::Yaml).map(Some)
+    }
+
+    /// 去掉路由前缀、前导斜杠与查询串并解码。拒绝空段与 `.`/`..`，
+    /// 避免键名拼入上游 URL 后被当作路径跳转到其他接口
+    fn relative_path(&self, normalized: &str) -> AppResult<String> {
+        let invalid = || AppError::BadRequest(format!("Invalid request path: {}", normalized));
+        let path = normalized.split('?').next().unwrap_or_default();
+        let path = path.strip_prefix(&self.route_prefix).ok_or_else(invalid)?;
+        let path = urlencoding::decode(path.trim_start_matches('/')).map_err(|_| invalid())?;
+        if path
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+            || path.chars().any(char::is_control)
+        {
+            return Err(invalid());
+        }
+        Ok(path.into_owned())
+    }
+}
+
+/// 按键的各段逐层插入，中间节点已是标量时替换为映射
+fn insert<'a>(node: &mut Value, mut segments: impl Iterator<Item = &'a str>, value: Value) {
+    let Some(segment) = segments.next() else {
+        *node = value;
+        return;
+    };
+    if !node.is_mapping() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    if let Value::Mapping(map) = node {
+        let child = map
+            .entry(Value::String(segment.to_string()))
+            .or_insert(Value::Null);
+        insert(child, segments, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(entries: &[(&str, &str)]) -> Vec<(String, Bytes)> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), Bytes::from(value.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn strips_route_prefix() {
+        let layout = KvLayout::new("/kv/**", "config");
+        assert_eq!(layout.key("/kv/app.yml").unwrap(), "config/app.yml");
+        assert_eq!(
+            layout.key("/kv/team/app.yml?x=1").unwrap(),
+            "config/team/app.yml"
+        );
+        assert_eq!(layout.tree_prefix("/kv/app.yml").unwrap(), "config/app/");
+
+        let layout = KvLayout::new("/kv/app-*.yml", "/config/");
+        assert_eq!(layout.key("/kv/app-dev.yml").unwrap(), "config/app-dev.yml");
+
+        let layout = KvLayout::new("/**", "");
+        assert_eq!(layout.key("/app.yml").unwrap(), "app.yml");
+    }
+
+    #[test]
+    fn rejects_paths_outside_route_or_with_traversal() {
+        let layout = KvLayout::new("/kv/**", "config/");
+        assert!(layout.key("/other/app.yml").is_err());
+        assert!(layout.key("/kv/../app.yml").is_err());
+        assert!(layout.key("/kv/a%2F..%2Fb.yml").is_err());
+        assert!(layout.key("/kv/a//b.yml").is_err());
+        assert!(layout.key("/kv/a%01.yml").is_err());
+    }
+
+    #[test]
+    fn affects_same_key_and_tree() {
+        let layout = KvLayout::new("/kv/**", "config/");
+        assert!(layout.affects("/kv/app.yml", "config/app.yml"));
+        assert!(layout.affects("/kv/app.yml", "config/app/db/url"));
+        assert!(!layout.affects("/kv/app.yml", "config/application/db/url"));
+        assert!(!layout.affects("/kv/app.yml", "config/other.yml"));
+    }
+
+    #[test]
+    fn renders_tree_with_typed_values() {
+        let layout = KvLayout::new("/kv/**", "config/");
+        let body = layout
+            .render_tree(
+                "config/app/",
+                entries(&[
+                    ("config/app/", ""),
+                    ("config/app/db/", ""),
+                    ("config/app/db/url", "jdbc:mysql://db"),
+                    ("config/app/db/pool", "10"),
+                    ("config/app/enabled", "true"),
+                    ("config/other/name", "ignored"),
+                ]),
+            )
+            .unwrap()
+            .unwrap();
+        let value: Value = serde_yml::from_slice(&body).unwrap();
+        assert_eq!(value["db"]["url"], Value::from("jdbc:mysql://db"));
+        assert_eq!(value["db"]["pool"], Value::from(10));
+        assert_eq!(value["enabled"], Value::from(true));
+        assert!(value.get("other").is_none());
+    }
+
+    #[test]
+    fn renders_nothing_without_keys() {
+        let layout = KvLayout::new("/kv/**", "config/");
+        let rendered = layout
+            .render_tree("config/app/", entries(&[("config/app/", "")]))
+            .unwrap();
+        assert!(rendered.is_none());
+    }
+}
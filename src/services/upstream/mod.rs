@@ -0,0 +1,215 @@
+mod consul;
+mod directory;
+mod etcd;
+mod git;
+mod http;
+mod kv;
+mod nacos;
+mod spring;
+
+use kv::KvLayout;
+
+pub use consul::ConsulUpstream;
+pub use directory::DirectoryUpstream;
+pub use etcd::EtcdUpstream;
+pub use git::GitUpstream;
+pub use http::HttpUpstream;
+pub use nacos::NacosUpstream;
+
+use crate::system::{RouteConfig, SystemConfig, UpstreamConfig, build_watch_client};
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use bytes::Bytes;
+use globset::{Glob, GlobMatcher};
+use reqwest::{Client, Response};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// 上游中的配置发生了变化
+pub struct UpstreamChange {
+    /// 发生变化的上游名称
+    pub upstream: String,
+    /// 变化的键，为空表示无法确定具体的键，该上游的所有缓存都受影响
+    pub keys: Vec<String>,
+}
+
+/// 配置来源。非 HTTP 的实现也返回 HTTP 响应，
+/// 由 `ProxyService` 统一处理状态码、响应体大小限制与熔断统计
+#[async_trait]
+pub trait Upstream: Send + Sync {
+    /// 上游名称，用作并发隔离的分组
+    fn name(&self) -> &str;
+
+    /// 校验请求路径并返回其在上游中的位置，用于日志与错误信息
+    fn locate(&self, normalized: &str) -> AppResult<String>;
+
+    /// 获取规范化路径对应的配置
+    async fn fetch(&self, normalized: &str, headers: &HeaderMap) -> AppResult<Response>;
+
+    /// 启动后台任务监听上游变化，不支持监听的上游无需实现
+    fn watch(self: Arc<Self>, _changes: UnboundedSender<UpstreamChange>) {}
+
+    /// 判断上游中变化的键是否影响规范化路径
+    fn affects(&self, _normalized: &str, _changed: &str) -> bool {
+        false
+    }
+
+    /// 是否按原始路径读取配置：不把 json/properties 请求改写为 yml，
+    /// 也接受没有扩展名的路径，内容原样返回
+    fn raw_paths(&self) -> bool {
+        false
+    }
+
+    /// 是否在读取配置时才登记监听，这类上游在启动时需要按缓存恢复监听
+    fn listens_on_read(&self) -> bool {
+        false
+    }
+
+    /// 按已缓存的内容恢复对规范化路径的监听
+    fn resume(&self, _normalized: &str, _content: &[u8]) {}
+}
+
+/// 按路由选择上游，与 `RouteTable` 一样取第一条匹配的路由，
+/// 路由未指定上游时使用 proxy_address
+pub struct UpstreamSet {
+    routes: Vec<(GlobMatcher, Option<Arc<dyn Upstream>>)>,
+    default: Arc<dyn Upstream>,
+}
+
+impl UpstreamSet {
+    pub fn new(config: &SystemConfig, http_client: Client) -> AppResult<Self> {
+        let default: Arc<dyn Upstream> = Arc::new(HttpUpstream::new(
+            &config.proxy_address,
+            http_client.clone(),
+            config,
+        )?);
+        let routes = config
+            .routes
+            .iter()
+            .map(|route| {
+                let matcher = Glob::new(&route.pattern)
+                    .map_err(|e| {
+                        AppError::Config(format!(
+                            "Invalid route pattern '{}': {}",
+                            route.pattern, e
+                        ))
+                    })?
+                    .compile_matcher();
+                let upstream = build(route, http_client.clone(), config)?;
+                Ok((matcher, upstream))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        Ok(Self { routes, default })
+    }
+
+    /// 所有上游，包括默认上游
+    pub fn upstreams(&self) -> Vec<Arc<dyn Upstream>> {
+        self.routes
+            .iter()
+            .filter_map(|(_, upstream)| upstream.clone())
+            .chain([self.default.clone()])
+            .collect()
+    }
+
+    /// 路由未指定上游时使用的默认上游
+    pub fn default_upstream(&self) -> &Arc<dyn Upstream> {
+        &self.default
+    }
+
+    /// 匹配规范化后的路径，查询串不参与匹配
+    pub fn find(&self, path: &str) -> &Arc<dyn Upstream> {
+        let file_path = path.split('?').next().unwrap_or_default();
+        self.routes
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(file_path))
+            .and_then(|(_, upstream)| upstream.as_ref())
+            .unwrap_or(&self.default)
+    }
+}
+
+fn build(
+    route: &RouteConfig,
+    http_client: Client,
+    config: &SystemConfig,
+) -> AppResult<Option<Arc<dyn Upstream>>> {
+    let upstream: Arc<dyn Upstream> = match &route.upstream {
+        None => return Ok(None),
+        Some(UpstreamConfig::Http { base_url }) => {
+            Arc::new(HttpUpstream::new(base_url, http_client, config)?)
+        }
+        Some(UpstreamConfig::Directory { path }) => Arc::new(DirectoryUpstream::new(path)?),
+        Some(UpstreamConfig::Git {
+            repository,
+            default_label,
+        }) => Arc::new(GitUpstream::new(repository, default_label)?),
+        Some(UpstreamConfig::Consul {
+            address,
+            prefix,
+            token,
+            datacenter,
+            wait_secs,
+        }) => Arc::new(ConsulUpstream::new(
+            address,
+            KvLayout::new(&route.pattern, prefix),
+            token.clone(),
+            datacenter.clone(),
+            *wait_secs,
+            http_client,
+            build_watch_client(&config.http_client)?,
+        )?),
+        Some(UpstreamConfig::Etcd {
+            address,
+            prefix,
+            progress_timeout_secs,
+        }) => Arc::new(EtcdUpstream::new(
+            address,
+            KvLayout::new(&route.pattern, prefix),
+            *progress_timeout_secs,
+            http_client,
+            build_watch_client(&config.http_client)?,
+        )?),
+        Some(UpstreamConfig::Nacos(nacos)) => Arc::new(NacosUpstream::new(
+            nacos,
+            http_client,
+            build_watch_client(&config.http_client)?,
+        )?),
+    };
+    Ok(Some(upstream))
+}
+
+/// 把读取的 YAML 配置包装为上游响应，找不到配置时返回 404
+fn yaml_response(body: Option<Bytes>) -> Response {
+    config_response(body, ConfigFormat::Yaml)
+}
+
+/// 把读取的配置按其格式包装为上游响应，找不到配置时返回 404
+fn config_response(body: Option<Bytes>, format: ConfigFormat) -> Response {
+    let found = body.is_some();
+    let mut response = axum::http::Response::new(body.unwrap_or_default());
+    if found {
+        let content_type = match format {
+            ConfigFormat::Yaml => "application/x-yaml",
+            ConfigFormat::Json => "application/json",
+            ConfigFormat::Properties => "text/x-java-properties",
+        };
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    } else {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+    }
+    Response::from(response)
+}
+
+/// 在本地随机端口启动模拟的上游服务，返回其地址
+#[cfg(test)]
+async fn serve_fake(router: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    format!("http://{}", address)
+}
@@ -0,0 +1,634 @@
+use super::{Upstream, UpstreamChange, config_response};
+use crate::system::NacosConfig;
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use md5::{Digest, Md5};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{Mutex as AsyncMutex, Notify};
+use url::Url;
+
+// 监听失败后重试的间隔
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+// 长轮询的超时余量，Nacos 在超时时间前约 500 毫秒返回
+const WATCH_TIMEOUT_MARGIN: Duration = Duration::from_secs(10);
+// 监听报文的字段与条目分隔符
+const FIELD_SEPARATOR: char = '\u{2}';
+const ENTRY_SEPARATOR: char = '\u{1}';
+// 访问令牌提前刷新的时间
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginResponse {
+    access_token: String,
+    token_ttl: u64,
+}
+
+/// 正在监听的配置及本副本最后一次读到的内容摘要
+struct Listening {
+    data_id: String,
+    group: String,
+    md5: String,
+}
+
+/// Nacos 上游：按 dataId/group/namespace 读取配置，
+/// 读取过的配置通过监听接口长轮询，变化时立即失效缓存
+pub struct NacosUpstream {
+    client: Client,
+    watch_client: Client,
+    base: Url,
+    config: NacosConfig,
+    name: String,
+    token: AsyncMutex<Option<(String, Instant)>>,
+    listening: Mutex<HashMap<String, Listening>>,
+    listening_changed: Notify,
+}
+
+impl NacosUpstream {
+    pub fn new(config: &NacosConfig, client: Client, watch_client: Client) -> AppResult<Self> {
+        let mut base = Url::parse(&config.address).map_err(|e| {
+            AppError::Config(format!("Invalid Nacos address '{}': {}", config.address, e))
+        })?;
+        if base.cannot_be_a_base() {
+            return Err(AppError::Config(format!(
+                "Invalid Nacos address '{}'",
+                config.address
+            )));
+        }
+        if config.username.is_some() != config.password.is_some() {
+            return Err(AppError::Config(
+                "Nacos username and password must be configured together".to_string(),
+            ));
+        }
+        if let Ok(mut segments) = base.path_segments_mut() {
+            segments.pop_if_empty();
+        }
+
+        Ok(Self {
+            client,
+            watch_client,
+            name: format!(
+                "nacos:{}/{}",
+                base.authority(),
+                config.namespace.as_deref().unwrap_or_default()
+            ),
+            base,
+            config: config.clone(),
+            token: AsyncMutex::new(None),
+            listening: Mutex::new(HashMap::new()),
+            listening_changed: Notify::new(),
+        })
+    }
+
+    /// 请求路径 `/{dataId}` 或 `/{group}/{dataId}` 对应的分组与 dataId
+    fn coordinates(&self, normalized: &str) -> AppResult<(String, String)> {
+        let path = normalized.split('?').next().unwrap_or_default();
+        let segments = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                urlencoding::decode(segment)
+                    .ok()
+                    .map(|decoded| decoded.into_owned())
+                    // 控制字符包括监听报文的分隔符
+                    .filter(|decoded| !decoded.chars().any(char::is_control))
+                    .ok_or_else(|| AppError::BadRequest(format!("Invalid request path: {}", path)))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        match segments.as_slice() {
+            [data_id] => Ok((self.config.group.clone(), data_id.clone())),
+            [group, data_id] => Ok((group.clone(), data_id.clone())),
+            _ => Err(AppError::BadRequest(format!(
+                "Expected /{{dataId}} or /{{group}}/{{dataId}}, got {}",
+                path
+            ))),
+        }
+    }
+
+    fn endpoint(&self, path: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.extend(path);
+        }
+        url
+    }
+
+    /// 附带命名空间与访问令牌
+    async fn authorize(&self, mut request: RequestBuilder) -> AppResult<RequestBuilder> {
+        if let Some(namespace) = &self.config.namespace {
+            request = request.query(&[("tenant", namespace)]);
+        }
+        if let Some(token) = self.access_token().await? {
+            request = request.query(&[("accessToken", token)]);
+        }
+        Ok(request)
+    }
+
+    /// 开启鉴权时登录获取访问令牌，在过期前复用
+    async fn access_token(&self) -> AppResult<Option<String>> {
+        let (Some(username), Some(password)) = (&self.config.username, &self.config.password)
+        else {
+            return Ok(None);
+        };
+
+        let mut token = self.token.lock().await;
+        if let Some((value, expires_at)) = token.as_ref()
+            && Instant::now() < *expires_at
+        {
+            return Ok(Some(value.clone()));
+        }
+
+        let url = self.endpoint(&["v1", "auth", "login"]);
+        let response = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(format!(
+                "username={}&password={}",
+                urlencoding::encode(username),
+                urlencoding::encode(password)
+            ))
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+        if !response.status().is_success() {
+            return Err(AppError::Proxy(format!(
+                "Nacos login returned status {}",
+                response.status()
+            )));
+        }
+        let login: LoginResponse = response.json().await.map_err(AppError::HttpClient)?;
+        let ttl = Duration::from_secs(login.token_ttl).saturating_sub(TOKEN_REFRESH_MARGIN);
+        *token = Some((login.access_token.clone(), Instant::now() + ttl));
+        Ok(Some(login.access_token))
+    }
+
+    /// 记录读取到的配置摘要，新增或摘要变化时让监听任务重新发起长轮询
+    fn listen(&self, data_id: &str, group: &str, md5: String) {
+        let mut listening = self
+            .listening
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = group_key(data_id, group);
+        if listening.get(&key).is_some_and(|entry| entry.md5 == md5) {
+            return;
+        }
+        listening.insert(
+            key,
+            Listening {
+                data_id: data_id.to_string(),
+                group: group.to_string(),
+                md5,
+            },
+        );
+        self.listening_changed.notify_one();
+    }
+
+    /// 拼接监听报文：`dataId^2group^2md5[^2tenant]^1`
+    fn listening_configs(&self) -> String {
+        let listening = self
+            .listening
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut configs = String::new();
+        for entry in listening.values() {
+            configs.push_str(&entry.data_id);
+            configs.push(FIELD_SEPARATOR);
+            configs.push_str(&entry.group);
+            configs.push(FIELD_SEPARATOR);
+            configs.push_str(&entry.md5);
+            if let Some(namespace) = &self.config.namespace {
+                configs.push(FIELD_SEPARATOR);
+                configs.push_str(namespace);
+            }
+            configs.push(ENTRY_SEPARATOR);
+        }
+        configs
+    }
+
+    /// 发起一次长轮询，返回发生变化的配置
+    async fn poll(&self, configs: &str) -> AppResult<Vec<String>> {
+        let timeout = Duration::from_secs(self.config.long_poll_secs.max(1));
+        let request = self
+            .watch_client
+            .post(self.endpoint(&["v1", "cs", "configs", "listener"]))
+            .header("Long-Pulling-Timeout", timeout.as_millis().to_string())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(format!(
+                "Listening-Configs={}",
+                urlencoding::encode(configs)
+            ))
+            .timeout(timeout + WATCH_TIMEOUT_MARGIN);
+        let response = self
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+        if !response.status().is_success() {
+            return Err(AppError::Proxy(format!(
+                "Nacos listener returned status {}",
+                response.status()
+            )));
+        }
+
+        // 响应体是经过 URL 编码的 `dataId^2group[^2tenant]^1` 列表
+        let body = response.text().await.map_err(AppError::HttpClient)?;
+        let body = urlencoding::decode(&body)
+            .map_err(|e| AppError::Proxy(format!("Invalid Nacos listener response: {}", e)))?;
+        Ok(body
+            .split(ENTRY_SEPARATOR)
+            .filter_map(|entry| {
+                let mut fields = entry.split(FIELD_SEPARATOR);
+                let data_id = fields.next().filter(|data_id| !data_id.is_empty())?;
+                let group = fields.next()?;
+                Some(group_key(data_id, group))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Upstream for NacosUpstream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn locate(&self, normalized: &str) -> AppResult<String> {
+        let (group, data_id) = self.coordinates(normalized)?;
+        Ok(format!("{}/{}/{}", self.name, group, data_id))
+    }
+
+    async fn fetch(&self, normalized: &str, _headers: &HeaderMap) -> AppResult<Response> {
+        let (group, data_id) = self.coordinates(normalized)?;
+        // dataId 的扩展名决定内容格式，没有扩展名时按 YAML 处理
+        let format = ConfigFormat::from_path(&data_id).unwrap_or(ConfigFormat::Yaml);
+        let request = self
+            .client
+            .get(self.endpoint(&["v1", "cs", "configs"]))
+            .query(&[("dataId", &data_id), ("group", &group)]);
+        let response = self
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .map_err(AppError::HttpClient)?;
+
+        match response.status() {
+            // 不存在的配置也加入监听，创建后立即失效缓存
+            StatusCode::NOT_FOUND => {
+                self.listen(&data_id, &group, String::new());
+                Ok(config_response(None, format))
+            }
+            status if status.is_success() => {
+                let body = response.bytes().await.map_err(AppError::HttpClient)?;
+                self.listen(&data_id, &group, hex::encode(Md5::digest(&body)));
+                Ok(config_response(Some(body), format))
+            }
+            status => Err(AppError::Proxy(format!(
+                "Nacos returned status {} for dataId {} in group {}",
+                status, data_id, group
+            ))),
+        }
+    }
+
+    fn watch(self: Arc<Self>, changes: UnboundedSender<UpstreamChange>) {
+        tokio::spawn(async move {
+            while !changes.is_closed() {
+                let configs = self.listening_configs();
+                if configs.is_empty() {
+                    self.listening_changed.notified().await;
+                    continue;
+                }
+
+                // 新增监听的配置时中断当前长轮询，带上新配置重新发起
+                let changed = tokio::select! {
+                    result = self.poll(&configs) => result,
+                    _ = self.listening_changed.notified() => continue,
+                };
+                let keys = match changed {
+                    Ok(keys) => keys,
+                    Err(e) => {
+                        tracing::warn!("Nacos listener on {} failed: {}", self.name, e);
+                        tokio::time::sleep(WATCH_RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                if keys.is_empty() {
+                    continue;
+                }
+
+                // 缓存失效后由下一次读取重新加入监听，避免旧摘要反复触发变化
+                {
+                    let mut listening = self
+                        .listening
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    for key in &keys {
+                        listening.remove(key);
+                    }
+                }
+                let change = UpstreamChange {
+                    upstream: self.name.clone(),
+                    keys,
+                };
+                if changes.send(change).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn affects(&self, normalized: &str, changed: &str) -> bool {
+        self.coordinates(normalized)
+            .is_ok_and(|(group, data_id)| group_key(&data_id, &group) == changed)
+    }
+
+    // dataId 可以是任意名称，扩展名是 dataId 的一部分
+    fn raw_paths(&self) -> bool {
+        true
+    }
+
+    fn listens_on_read(&self) -> bool {
+        true
+    }
+
+    /// 缓存内容经过补丁或解密时摘要与上游不同，Nacos 会立即报告变化并失效一次缓存
+    fn resume(&self, normalized: &str, content: &[u8]) {
+        let Ok((group, data_id)) = self.coordinates(normalized) else {
+            return;
+        };
+        let mut listening = self
+            .listening
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // 已经由读取登记的监听使用上游的真实摘要
+        let key = group_key(&data_id, &group);
+        if listening.contains_key(&key) {
+            return;
+        }
+        listening.insert(
+            key,
+            Listening {
+                data_id,
+                group,
+                md5: hex::encode(Md5::digest(content)),
+            },
+        );
+        self.listening_changed.notify_one();
+    }
+}
+
+/// 监听变化的键。与监听报文一样用字段分隔符连接，dataId 与分组中不会出现控制字符，
+/// 不会像 `+` 那样产生歧义
+fn group_key(data_id: &str, group: &str) -> String {
+    format!("{}{}{}", data_id, FIELD_SEPARATOR, group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::upstream::serve_fake;
+    use axum::{
+        Form, Json, Router,
+        extract::{Query, State},
+        response::{IntoResponse, Response as AxumResponse},
+        routing::{get, post},
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc;
+
+    const NAMESPACE: &str = "dev";
+
+    /// 模拟 Nacos：登录签发递增的令牌，读取配置校验令牌与命名空间，
+    /// 监听接口记录请求报文并依次返回给定的变化，用完后一直等待
+    #[derive(Clone)]
+    struct FakeNacos {
+        configs: Arc<HashMap<(String, String), String>>,
+        token_ttl: u64,
+        logins: Arc<AtomicUsize>,
+        tokens: Arc<Mutex<Vec<String>>>,
+        listening: Arc<Mutex<Vec<String>>>,
+        changes: Arc<Mutex<Vec<String>>>,
+    }
+
+    async fn login(
+        State(fake): State<FakeNacos>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> AxumResponse {
+        if form.get("username").map(String::as_str) != Some("nacos")
+            || form.get("password").map(String::as_str) != Some("p@ss&word")
+        {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let login = fake.logins.fetch_add(1, Ordering::Relaxed) + 1;
+        Json(json!({ "accessToken": format!("token-{}", login), "tokenTtl": fake.token_ttl }))
+            .into_response()
+    }
+
+    async fn config(
+        State(fake): State<FakeNacos>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> AxumResponse {
+        let Some(token) = query.get("accessToken") else {
+            return StatusCode::FORBIDDEN.into_response();
+        };
+        fake.tokens.lock().unwrap().push(token.clone());
+        if query.get("tenant").map(String::as_str) != Some(NAMESPACE) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        let key = (query["group"].clone(), query["dataId"].clone());
+        match fake.configs.get(&key) {
+            Some(content) => content.clone().into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    async fn listener(
+        State(fake): State<FakeNacos>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> AxumResponse {
+        fake.listening
+            .lock()
+            .unwrap()
+            .push(form["Listening-Configs"].clone());
+        let change = fake.changes.lock().unwrap().pop();
+        match change {
+            Some(change) => urlencoding::encode(&change).into_owned().into_response(),
+            None => {
+                std::future::pending::<()>().await;
+                unreachable!();
+            }
+        }
+    }
+
+    async fn fake_nacos(
+        configs: &[(&str, &str, &str)],
+        token_ttl: u64,
+        changes: &[&str],
+    ) -> (Arc<NacosUpstream>, FakeNacos) {
+        let fake = FakeNacos {
+            configs: Arc::new(
+                configs
+                    .iter()
+                    .map(|(group, data_id, content)| {
+                        (
+                            (group.to_string(), data_id.to_string()),
+                            content.to_string(),
+                        )
+                    })
+                    .collect(),
+            ),
+            token_ttl,
+            logins: Arc::new(AtomicUsize::new(0)),
+            tokens: Arc::new(Mutex::new(Vec::new())),
+            listening: Arc::new(Mutex::new(Vec::new())),
+            changes: Arc::new(Mutex::new(
+                changes
+                    .iter()
+                    .rev()
+                    .map(|change| change.to_string())
+                    .collect(),
+            )),
+        };
+        let router = Router::new()
+            .route("/nacos/v1/auth/login", post(login))
+            .route("/nacos/v1/cs/configs", get(config))
+            .route("/nacos/v1/cs/configs/listener", post(listener))
+            .with_state(fake.clone());
+        let address = serve_fake(router).await;
+        let config = NacosConfig {
+            address: format!("{}/nacos/", address),
+            namespace: Some(NAMESPACE.to_string()),
+            group: "DEFAULT_GROUP".to_string(),
+            username: Some("nacos".to_string()),
+            password: Some("p@ss&word".to_string()),
+            long_poll_secs: 1,
+        };
+        let nacos = NacosUpstream::new(&config, Client::new(), Client::new()).unwrap();
+        (Arc::new(nacos), fake)
+    }
+
+    #[tokio::test]
+    async fn maps_paths_to_group_and_data_id() {
+        let (nacos, _) = fake_nacos(&[], 18000, &[]).await;
+        let coordinates = |path: &str| nacos.coordinates(path).ok();
+
+        assert_eq!(
+            coordinates("/app.yml?x=1"),
+            Some(("DEFAULT_GROUP".to_string(), "app.yml".to_string()))
+        );
+        assert_eq!(
+            coordinates("/SHARED/app%20v2.json"),
+            Some(("SHARED".to_string(), "app v2.json".to_string()))
+        );
+        assert_eq!(
+            coordinates("/app"),
+            Some(("DEFAULT_GROUP".to_string(), "app".to_string()))
+        );
+        assert_eq!(coordinates("/"), None);
+        assert_eq!(coordinates("/a/b/c.yml"), None);
+        assert_eq!(coordinates("/app%02.yml"), None);
+        assert!(
+            nacos
+                .locate("/SHARED/app.yml")
+                .unwrap()
+                .ends_with("/dev/SHARED/app.yml")
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_configs_with_their_format() {
+        let (nacos, fake) = fake_nacos(
+            &[
+                ("DEFAULT_GROUP", "app.yml", "name: app"),
+                ("SHARED", "db.json", r#"{"url":"jdbc"}"#),
+            ],
+            18000,
+            &[],
+        )
+        .await;
+        let headers = HeaderMap::new();
+
+        let response = nacos.fetch("/app.yml", &headers).await.unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-yaml"
+        );
+        assert_eq!(response.bytes().await.unwrap(), "name: app");
+
+        let response = nacos.fetch("/SHARED/db.json", &headers).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.bytes().await.unwrap(), r#"{"url":"jdbc"}"#);
+
+        let response = nacos.fetch("/missing.yml", &headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // 令牌在有效期内复用
+        assert_eq!(fake.logins.load(Ordering::Relaxed), 1);
+        assert_eq!(*fake.tokens.lock().unwrap(), ["token-1"; 3]);
+    }
+
+    #[tokio::test]
+    async fn logs_in_again_when_token_expires() {
+        // 有效期短于提前刷新的时间，每次请求都重新登录
+        let (nacos, fake) = fake_nacos(&[("DEFAULT_GROUP", "app.yml", "a: 1")], 30, &[]).await;
+        let headers = HeaderMap::new();
+        nacos.fetch("/app.yml", &headers).await.unwrap();
+        nacos.fetch("/app.yml", &headers).await.unwrap();
+
+        assert_eq!(fake.logins.load(Ordering::Relaxed), 2);
+        assert_eq!(*fake.tokens.lock().unwrap(), ["token-1", "token-2"]);
+    }
+
+    #[tokio::test]
+    async fn reports_changes_from_listener() {
+        let (nacos, fake) = fake_nacos(
+            &[("DEFAULT_GROUP", "app+v1.yml", "name: app")],
+            18000,
+            &["app+v1.yml\u{2}DEFAULT_GROUP\u{2}dev\u{1}\u{1}"],
+        )
+        .await;
+        let response = nacos.fetch("/app+v1.yml", &HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // 不存在的配置也登记监听，摘要为空
+        nacos
+            .fetch("/missing.yml", &HeaderMap::new())
+            .await
+            .unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        nacos.clone().watch(sender);
+        let change = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.keys, [group_key("app+v1.yml", "DEFAULT_GROUP")]);
+        assert!(nacos.affects("/app+v1.yml", &change.keys[0]));
+        assert!(!nacos.affects("/app.yml", &change.keys[0]));
+
+        let mut entries: Vec<String> = fake.listening.lock().unwrap()[0]
+            .split_terminator(ENTRY_SEPARATOR)
+            .map(str::to_string)
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [
+                format!(
+                    "app+v1.yml\u{2}DEFAULT_GROUP\u{2}{}\u{2}dev",
+                    hex::encode(Md5::digest("name: app"))
+                ),
+                "missing.yml\u{2}DEFAULT_GROUP\u{2}\u{2}dev".to_string(),
+            ]
+        );
+    }
+}
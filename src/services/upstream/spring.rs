@@ -0,0 +1,330 @@
+use crate::services::format_converter;
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_yml::Value;
+
+// 对所有应用生效的公共配置文件名
+const SHARED_APPLICATION: &str = "application";
+// 未指定 profile 时激活的 profile
+const DEFAULT_PROFILE: &str = "default";
+// 仓库中按顺序查找的扩展名
+pub const EXTENSIONS: [&str; 2] = ["yml", "yaml"];
+
+/// 按 Spring 配置中心的规则解析的请求路径：
+/// `/{application}-{profiles}.yml` 或 `/{label}/{application}-{profiles}.yml`
+pub struct SpringPath {
+    pub applications: Vec<String>,
+    pub profiles: Vec<String>,
+    pub label: Option<String>,
+}
+
+impl SpringPath {
+    pub fn parse(normalized: &str) -> AppResult<Self> {
+        let path = normalized.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let (label, file) = match segments.as_slice() {
+            [file] => (None, *file),
+            // Spring 用 `(_)` 代替标签中的斜杠
+            [label, file] => (Some(decode(label)?.replace("(_)", "/")), *file),
+            _ => return Err(unsupported(path)),
+        };
+        let stem = match file.rsplit_once('.') {
+            Some((stem, _)) if ConfigFormat::from_path(file) == Some(ConfigFormat::Yaml) => stem,
+            _ => return Err(unsupported(path)),
+        };
+
+        // 应用名可能含有 `-`，与 Spring 一样按最后一个 `-` 切分
+        let stem = decode(stem)?;
+        let (applications, profiles) = match stem.rsplit_once('-') {
+            Some((applications, profiles)) if !applications.is_empty() && !profiles.is_empty() => {
+                (applications, profiles)
+            }
+            _ => (stem.as_str(), DEFAULT_PROFILE),
+        };
+        let split = |list: &str| -> AppResult<Vec<String>> {
+            list.split(',')
+                .map(|name| {
+                    if name.is_empty()
+                        || name.starts_with('.')
+                        || name.contains(['/', '\\'])
+                        || name.chars().any(char::is_control)
+                    {
+                        Err(AppError::BadRequest(format!(
+                            "Invalid application or profile name in {}",
+                            path
+                        )))
+                    } else {
+                        Ok(name.to_string())
+                    }
+                })
+                .collect()
+        };
+
+        Ok(Self {
+            applications: split(applications)?,
+            profiles: split(profiles)?,
+            label,
+        })
+    }
+
+    /// 候选文件名（不含扩展名），按优先级从低到高排列：
+    /// profile 专属文件覆盖通用文件，应用专属文件覆盖公共的 application 文件
+    pub fn candidates(&self) -> Vec<String> {
+        let mut names = vec![SHARED_APPLICATION.to_string()];
+        names.extend(self.applications.iter().cloned());
+        for profile in &self.profiles {
+            names.push(format!("{}-{}", SHARED_APPLICATION, profile));
+            names.extend(
+                self.applications
+                    .iter()
+                    .map(|application| format!("{}-{}", application, profile)),
+            );
+        }
+
+        let mut unique = Vec::with_capacity(names.len());
+        for name in names {
+            if !unique.contains(&name) {
+                unique.push(name);
+            }
+        }
+        unique
+    }
+
+    /// 按优先级从低到高合并找到的文件，跳过未激活 profile 的文档。没有任何文件时返回 None
+    pub fn merge(&self, sources: &[(String, Bytes)]) -> AppResult<Option<Bytes>> {
+        if sources.is_empty() {
+            return Ok(None);
+        }
+
+        let mut merged = Value::Null;
+        for (name, content) in sources {
+            for document in serde_yml::Deserializer::from_slice(content) {
+                let mut value = Value::deserialize(document)
+                    .map_err(|e| AppError::Proxy(format!("Invalid YAML in {}: {}", name, e)))?;
+                if self.is_active(&value) {
+                    strip_activation(&mut value);
+                    format_converter::merge_yaml(&mut merged, value);
+                }
+            }
+        }
+        format_converter::render(&merged, ConfigFormat::Yaml).map(Some)
+    }
+
+    /// 文档通过 `spring.config.activate.on-profile`（或旧版的 `spring.profiles`）
+    /// 限定 profile 时，只有其中任一表达式匹配才生效，`!profile` 表示该 profile 未激活
+    fn is_active(&self, document: &Value) -> bool {
+        let spring = document.get("spring");
+        let condition = spring
+            .and_then(|spring| spring.get("config"))
+            .and_then(|config| config.get("activate"))
+            .and_then(|activate| activate.get("on-profile"))
+            .or_else(|| spring.and_then(|spring| spring.get("profiles")));
+        let expressions: Vec<&str> = match condition {
+            None => return true,
+            Some(Value::String(list)) => list.split(',').map(str::trim).collect(),
+            Some(Value::Sequence(items)) => items.iter().filter_map(Value::as_str).collect(),
+            Some(_) => return true,
+        };
+
+        expressions
+            .iter()
+            .any(|expression| match expression.strip_prefix('!') {
+                Some(profile) => !self.profiles.iter().any(|active| active == profile.trim()),
+                None => self.profiles.iter().any(|active| active == expression),
+            })
+    }
+}
+
+/// 移除已经处理过的激活条件，避免合并结果被客户端再次按 profile 过滤
+fn strip_activation(document: &mut Value) {
+    let Some(Value::Mapping(spring)) = document.get_mut("spring") else {
+        return;
+    };
+    spring.shift_remove("profiles");
+    if let Some(Value::Mapping(config)) = spring.get_mut("config") {
+        if let Some(Value::Mapping(activate)) = config.get_mut("activate") {
+            activate.shift_remove("on-profile");
+            if activate.is_empty() {
+                config.shift_remove("activate");
+            }
+        }
+        if config.is_empty() {
+            spring.shift_remove("config");
+        }
+    }
+    if spring.is_empty()
+        && let Value::Mapping(root) = document
+    {
+        root.shift_remove("spring");
+    }
+}
+
+fn decode(segment: &str) -> AppResult<String> {
+    urlencoding::decode(segment)
+        .map(|decoded| decoded.into_owned())
+        .map_err(|_| AppError::BadRequest(format!("Invalid path segment: {}", segment)))
+}
+
+fn unsupported(path: &str) -> AppError {
+    AppError::BadRequest(format!(
+        "Expected /{{application}}-{{profile}}.yml or /{{label}}/{{application}}-{{profile}}.yml, got {}",
+        path
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(path: &str, sources: &[(&str, &str)]) -> Value {
+        let sources: Vec<(String, Bytes)> = sources
+            .iter()
+            .map(|(name, content)| (name.to_string(), Bytes::from(content.to_string())))
+            .collect();
+        let body = SpringPath::parse(path)
+            .unwrap()
+            .merge(&sources)
+            .unwrap()
+            .unwrap();
+        serde_yml::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn parses_application_profiles_and_label() {
+        let path = SpringPath::parse("/main/order-service-prod,db.yml?x=1").unwrap();
+        assert_eq!(path.applications, ["order-service"]);
+        assert_eq!(path.profiles, ["prod", "db"]);
+        assert_eq!(path.label.as_deref(), Some("main"));
+
+        let path = SpringPath::parse("/release(_)1.0/app-dev.yaml").unwrap();
+        assert_eq!(path.label.as_deref(), Some("release/1.0"));
+    }
+
+    #[test]
+    fn defaults_profile_without_dash() {
+        let path = SpringPath::parse("/app.yml").unwrap();
+        assert_eq!(path.applications, ["app"]);
+        assert_eq!(path.profiles, ["default"]);
+        assert_eq!(path.label, None);
+
+        // 开头或结尾的 `-` 不作为分隔符
+        let path = SpringPath::parse("/app-.yml").unwrap();
+        assert_eq!(path.applications, ["app-"]);
+        assert_eq!(path.profiles, ["default"]);
+    }
+
+    #[test]
+    fn rejects_unsupported_paths() {
+        for path in [
+            "/app.properties",
+            "/app",
+            "/a/b/app-dev.yml",
+            "/",
+            "/app-dev%2F..%2Fsecret.yml",
+            "/app-..hidden.yml",
+            "/app-dev,,prod.yml",
+            "/app-dev%00.yml",
+        ] {
+            assert!(SpringPath::parse(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn orders_candidates_by_spring_precedence() {
+        let path = SpringPath::parse("/app-dev,db.yml").unwrap();
+        assert_eq!(
+            path.candidates(),
+            [
+                "application",
+                "app",
+                "application-dev",
+                "app-dev",
+                "application-db",
+                "app-db"
+            ]
+        );
+
+        // 应用名为 application 时不重复
+        let path = SpringPath::parse("/application-dev.yml").unwrap();
+        assert_eq!(path.candidates(), ["application", "application-dev"]);
+    }
+
+    #[test]
+    fn later_sources_override_earlier_ones() {
+        let value = merged(
+            "/app-dev.yml",
+            &[
+                ("application.yml", "a: shared\nb: shared\nc: shared\n"),
+                ("app.yml", "b: app\nc: app\n"),
+                ("app-dev.yml", "c: app-dev\n"),
+            ],
+        );
+        assert_eq!(value["a"].as_str(), Some("shared"));
+        assert_eq!(value["b"].as_str(), Some("app"));
+        assert_eq!(value["c"].as_str(), Some("app-dev"));
+    }
+
+    #[test]
+    fn applies_only_active_profile_documents() {
+        let content = "\
+a: base
+---
+spring:
+  config:
+    activate:
+      on-profile: dev
+a: dev
+---
+spring:
+  profiles: prod
+a: prod
+---
+spring:
+  config:
+    activate:
+      on-profile: '!dev'
+b: not-dev
+";
+        let value = merged("/app-dev.yml", &[("app.yml", content)]);
+        assert_eq!(value["a"].as_str(), Some("dev"));
+        assert!(value.get("b").is_none());
+        // 处理过的激活条件不出现在结果中
+        assert!(value.get("spring").is_none());
+
+        let value = merged("/app-prod.yml", &[("app.yml", content)]);
+        assert_eq!(value["a"].as_str(), Some("prod"));
+        assert_eq!(value["b"].as_str(), Some("not-dev"));
+    }
+
+    #[test]
+    fn matches_any_expression_in_profile_list() {
+        let path = SpringPath::parse("/app-db.yml").unwrap();
+        let document: Value = serde_yml::from_str("spring:\n  profiles: [dev, db]\n").unwrap();
+        assert!(path.is_active(&document));
+        let document: Value = serde_yml::from_str("spring:\n  profiles: dev, prod\n").unwrap();
+        assert!(!path.is_active(&document));
+        let document: Value = serde_yml::from_str("a: 1\n").unwrap();
+        assert!(path.is_active(&document));
+    }
+
+    #[test]
+    fn keeps_other_spring_settings_when_stripping_activation() {
+        let value = merged(
+            "/app-dev.yml",
+            &[(
+                "app.yml",
+                "spring:\n  config:\n    activate:\n      on-profile: dev\n  application:\n    name: app\n",
+            )],
+        );
+        assert_eq!(value["spring"]["application"]["name"].as_str(), Some("app"));
+        assert!(value["spring"].get("config").is_none());
+    }
+
+    #[test]
+    fn returns_none_without_sources() {
+        let path = SpringPath::parse("/app.yml").unwrap();
+        assert!(path.merge(&[]).unwrap().is_none());
+    }
+}
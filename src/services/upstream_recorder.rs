@@ -0,0 +1,296 @@
+use crate::models::recording::Recording;
+use crate::system::{RecordingConfig, RecordingMode};
+use crate::utils::errors::{AppError, AppResult};
+use axum::http::{self, HeaderName, HeaderValue};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::{Bytes, BytesMut};
+use futures_util::{StreamExt, stream};
+use reqwest::{Body, Response};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+// 描述传输分帧的逐跳响应头，回放时由服务端按实际响应体重新生成
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// 录制上游响应或从录制文件回放。每个录制键（规范化路径与 vary 请求头）对应一个
+/// 以键哈希命名的 JSON 文件，重复录制时覆盖旧文件
+pub struct UpstreamRecorder {
+    mode: RecordingMode,
+    directory: PathBuf,
+    max_body_size: u64,
+}
+
+impl UpstreamRecorder {
+    /// `max_body_size` 与代理读取上游响应体的限制一致，超出的响应不录制
+    pub fn new(config: &RecordingConfig, max_body_size: u64) -> AppResult<Self> {
+        let directory = PathBuf::from(&config.directory);
+        match config.mode {
+            RecordingMode::Record => std::fs::create_dir_all(&directory).map_err(|e| {
+                AppError::Config(format!(
+                    "Failed to create recording directory '{}': {}",
+                    config.directory, e
+                ))
+            })?,
+            RecordingMode::Replay if !directory.is_dir() => {
+                return Err(AppError::Config(format!(
+                    "Recording directory '{}' does not exist",
+                    config.directory
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            mode: config.mode,
+            directory,
+            max_body_size,
+        })
+    }
+
+    pub fn mode(&self) -> RecordingMode {
+        self.mode
+    }
+
+    /// 检查录制键是否有录制文件，回放模式下用于在访问上游流程之前返回 404
+    pub async fn has_recording(&self, key: &str) -> bool {
+        fs::metadata(self.recording_path(key)).await.is_ok()
+    }
+
+    /// 读取录制文件并还原为上游响应
+    pub async fn replay(&self, key: &str) -> AppResult<Response> {
+        let content = match fs::read(self.recording_path(key)).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AppError::NotFound(format!(
+                    "No recording for {} in replay mode",
+                    key
+                )));
+            }
+            Err(e) => return Err(AppError::Io(e)),
+        };
+        let recording: Recording = serde_json::from_slice(&content)?;
+
+        let body = match (recording.body, recording.body_base64) {
+            (Some(body), _) => Bytes::from(body),
+            (None, Some(encoded)) => Bytes::from(STANDARD.decode(encoded).map_err(|e| {
+                AppError::Internal(format!("Invalid recording body for {}: {}", key, e))
+            })?),
+            (None, None) => Bytes::new(),
+        };
+        let mut builder = http::Response::builder().status(recording.status);
+        for (name, value) in &recording.headers {
+            if is_hop_by_hop(name) {
+                continue;
+            }
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                builder = builder.header(name, value);
+            }
+        }
+        let response = builder
+            .body(body)
+            .map_err(|e| AppError::Internal(format!("Invalid recording for {}: {}", key, e)))?;
+        Ok(Response::from(response))
+    }
+
+    /// 读取完整响应体并写入录制文件，返回内容相同的新响应。只录制成功的响应，
+    /// 上游的错误不会覆盖已有的录制。响应体超过 `max_body_size` 时不录制，
+    /// 已读取的部分与剩余部分拼接后原样返回，由代理按超限策略处理。录制失败只记录日志，不影响本次请求
+    pub async fn record(&self, path: &str, key: &str, response: Response) -> AppResult<Response> {
+        let status = response.status();
+        if !status.is_success() {
+            return Ok(response);
+        }
+        let oversize = || {
+            tracing::warn!(
+                "Upstream body for {} exceeds max_body_size of {} bytes, not recording",
+                path,
+                self.max_body_size
+            );
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_body_size)
+        {
+            oversize();
+            return Ok(response);
+        }
+        let headers = response.headers().clone();
+
+        let mut body_stream = response.bytes_stream();
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = body_stream.next().await {
+            let chunk = chunk.map_err(AppError::HttpClient)?;
+            if (buffer.len() + chunk.len()) as u64 > self.max_body_size {
+                oversize();
+                let head = stream::iter([Ok(buffer.freeze()), Ok(chunk)]);
+                let body = Body::wrap_stream(head.chain(body_stream));
+                return Ok(rebuild(status, headers, body));
+            }
+            buffer.extend_from_slice(&chunk);
+        }
+        let body = buffer.freeze();
+
+        let (text, encoded) = match std::str::from_utf8(&body) {
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (None, Some(STANDARD.encode(&body))),
+        };
+        let recording = Recording {
+            path: path.to_string(),
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| !is_hop_by_hop(name.as_str()))
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: text,
+            body_base64: encoded,
+            recorded_unix_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+        };
+        match serde_json::to_vec_pretty(&recording) {
+            Ok(content) => {
+                if let Err(e) = fs::write(self.recording_path(key), content).await {
+                    tracing::warn!("Failed to record upstream response for {}: {}", path, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to encode recording for {}: {}", path, e),
+        }
+
+        Ok(rebuild(status, headers, Body::from(body)))
+    }
+
+    fn recording_path(&self, key: &str) -> PathBuf {
+        let name = hex::encode(Sha256::digest(key.as_bytes()));
+        self.directory.join(format!("{}.json", &name[..16]))
+    }
+}
+
+fn rebuild(status: http::StatusCode, headers: http::HeaderMap, body: Body) -> Response {
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    Response::from(rebuilt)
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS
+        .iter()
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(name: &str, max_body_size: u64) -> UpstreamRecorder {
+        let directory = std::env::temp_dir().join(format!(
+            "config-cache-proxy-recordings-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        UpstreamRecorder::new(
+            &RecordingConfig {
+                mode: RecordingMode::Record,
+                directory: directory.to_string_lossy().into_owned(),
+            },
+            max_body_size,
+        )
+        .unwrap()
+    }
+
+    fn upstream(status: u16, body: &'static str) -> Response {
+        let response = http::Response::builder()
+            .status(status)
+            .header("content-type", "application/x-yaml")
+            .header("content-length", body.len())
+            .header("transfer-encoding", "chunked")
+            .header("connection", "keep-alive")
+            .header("x-upstream", "config-server")
+            .body(body)
+            .unwrap();
+        Response::from(response)
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_response_without_framing_headers() {
+        let recorder = recorder("round-trip", 1024);
+        let recorded = recorder
+            .record(
+                "/app-prod.yml",
+                "v3:/app-prod.yml",
+                upstream(200, "key: value\n"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(recorded.text().await.unwrap(), "key: value\n");
+        assert!(recorder.has_recording("v3:/app-prod.yml").await);
+
+        let replayed = recorder.replay("v3:/app-prod.yml").await.unwrap();
+        assert_eq!(replayed.status(), 200);
+        let headers = replayed.headers();
+        assert_eq!(headers["content-type"], "application/x-yaml");
+        assert_eq!(headers["x-upstream"], "config-server");
+        for header in ["content-length", "transfer-encoding", "connection"] {
+            assert!(!headers.contains_key(header), "{} was replayed", header);
+        }
+        assert_eq!(replayed.text().await.unwrap(), "key: value\n");
+    }
+
+    #[tokio::test]
+    async fn skips_error_and_oversize_responses() {
+        let recorder = recorder("skipped", 4);
+        let failed = recorder
+            .record("/a.yml", "v3:/a.yml", upstream(500, "error"))
+            .await
+            .unwrap();
+        assert_eq!(failed.status(), 500);
+        assert!(!recorder.has_recording("v3:/a.yml").await);
+
+        let oversize = recorder
+            .record("/b.yml", "v3:/b.yml", upstream(200, "key: value\n"))
+            .await
+            .unwrap();
+        assert_eq!(oversize.text().await.unwrap(), "key: value\n");
+        assert!(!recorder.has_recording("v3:/b.yml").await);
+
+        let missing = recorder.replay("v3:/b.yml").await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn passes_through_chunked_oversize_body_without_recording() {
+        let recorder = recorder("chunked", 8);
+        let chunks = stream::iter([
+            Ok::<_, std::io::Error>(Bytes::from_static(b"key: ")),
+            Ok(Bytes::from_static(b"value\n")),
+        ]);
+        let response = Response::from(http::Response::new(Body::wrap_stream(chunks)));
+        assert!(response.content_length().is_none());
+
+        let passed = recorder
+            .record("/c.yml", "v3:/c.yml", response)
+            .await
+            .unwrap();
+        assert_eq!(passed.text().await.unwrap(), "key: value\n");
+        assert!(!recorder.has_recording("v3:/c.yml").await);
+    }
+}
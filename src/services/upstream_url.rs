@@ -0,0 +1,226 @@
+use crate::utils::errors::{AppError, AppResult};
+use url::Url;
+
+/// 负责把请求路径安全地解析到上游 Base URL 之下，
+/// 拒绝任何试图跳出 Base 路径或改变目标主机的请求
+#[derive(Clone)]
+pub struct UpstreamUrlResolver {
+    base: Url,
+}
+
+impl UpstreamUrlResolver {
+    pub fn new(base_url: &str) -> AppResult<Self> {
+        let mut base = Url::parse(base_url).map_err(|e| {
+            AppError::Config(format!("Invalid upstream base URL '{}': {}", base_url, e))
+        })?;
+
+        if !matches!(base.scheme(), "http" | "https") || base.host().is_none() {
+            return Err(AppError::Config(format!(
+                "Upstream base URL must be an absolute http(s) URL: {}",
+                base_url
+            )));
+        }
+
+        // Base 路径必须以斜杠结尾，否则相对解析会丢弃最后一段
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        base.set_query(None);
+        base.set_fragment(None);
+
+        Ok(Self { base })
+    }
+
+    pub fn base(&self) -> &Url {
+        &self.base
+    }
+
+    /// 将规范化后的请求路径（含查询串）解析为上游 URL
+    pub fn resolve(&self, path_and_query: &str) -> AppResult<Url> {
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+
+        // 只接受 origin-form 路径，绝对 URL 或协议相对路径可能改变目标主机
+        let Some(relative) = path.strip_prefix('/') else {
+            return Err(rejected(path_and_query, "path must start with '/'"));
+        };
+        if relative.starts_with('/') {
+            return Err(rejected(path_and_query, "protocol-relative path"));
+        }
+
+        for segment in relative.split('/') {
+            check_segment(segment).map_err(|reason| rejected(path_and_query, reason))?;
+        }
+
+        // 加上 "./" 前缀，避免形如 "host:port" 的首段被当作 scheme 解析
+        let mut url = self
+            .base
+            .join(&format!("./{}", relative))
+            .map_err(|e| rejected(path_and_query, &e.to_string()))?;
+        url.set_query(query);
+
+        if url.scheme() != self.base.scheme()
+            || url.host() != self.base.host()
+            || url.port_or_known_default() != self.base.port_or_known_default()
+            || !url.username().is_empty()
+            || url.password().is_some()
+        {
+            return Err(rejected(path_and_query, "upstream authority changed"));
+        }
+        if !url.path().starts_with(self.base.path()) {
+            return Err(rejected(path_and_query, "path escapes upstream base path"));
+        }
+
+        Ok(url)
+    }
+}
+
+/// 校验单个路径段：解码后不得为 `.`/`..`，也不得包含分隔符或控制字符
+fn check_segment(segment: &str) -> Result<(), &'static str> {
+    if segment.contains('\\') {
+        return Err("backslash in path");
+    }
+
+    let decoded: Vec<u8> = urlencoding::decode_binary(segment.as_bytes()).into_owned();
+    // 多重编码（如 %252e）在上游可能被再次解码，一并拒绝
+    if decoded.contains(&b'%') {
+        return Err("nested percent-encoding in path");
+    }
+    if decoded == b"." || decoded == b".." {
+        return Err("dot segment in path");
+    }
+    if decoded
+        .iter()
+        .any(|b| matches!(b, b'/' | b'\\') || b.is_ascii_control())
+    {
+        return Err("encoded separator or control character in path");
+    }
+
+    Ok(())
+}
+
+fn rejected(path: &str, reason: &str) -> AppError {
+    tracing::warn!("Rejected upstream path {:?}: {}", path, reason);
+    AppError::BadRequest(format!("Invalid request path: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> UpstreamUrlResolver {
+        UpstreamUrlResolver::new("http://config.internal:8888/base").unwrap()
+    }
+
+    fn assert_rejected(path: &str) {
+        assert!(
+            resolver().resolve(path).is_err(),
+            "expected {:?} to be rejected",
+            path
+        );
+    }
+
+    #[test]
+    fn resolves_paths_under_base() {
+        let url = resolver().resolve("/app/application.yml?a=1").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "http://config.internal:8888/base/app/application.yml?a=1"
+        );
+    }
+
+    #[test]
+    fn rejects_dot_segments() {
+        for path in [
+            "/..",
+            "/../app.yml",
+            "/app/../../app.yml",
+            "/.",
+            "/./app.yml",
+            "/%2e%2e/app.yml",
+            "/%2E%2E/app.yml",
+            "/%2E%2e/app.yml",
+            "/.%2e/app.yml",
+            "/%2e/app.yml",
+            "/%252e%252e/app.yml",
+        ] {
+            assert_rejected(path);
+        }
+    }
+
+    #[test]
+    fn rejects_encoded_separators_and_backslashes() {
+        for path in [
+            "/app%2fapplication.yml",
+            "/app%2Fapplication.yml",
+            "/..%2fapp.yml",
+            "/app%5capplication.yml",
+            "/app\\application.yml",
+            "/..\\app.yml",
+            "/\\evil.example/app.yml",
+        ] {
+            assert_rejected(path);
+        }
+    }
+
+    #[test]
+    fn rejects_absolute_and_authority_injection() {
+        for path in [
+            "http://evil.example/app.yml",
+            "https://evil.example/app.yml",
+            "app.yml",
+            "//evil.example/app.yml",
+            "///evil.example/app.yml",
+            "/%2f%2fevil.example/app.yml",
+            "/http:%2f%2fevil.example/app.yml",
+        ] {
+            assert_rejected(path);
+        }
+    }
+
+    #[test]
+    fn keeps_scheme_like_segments_under_base() {
+        for path in [
+            "/evil.example:80/app.yml",
+            "/http:/app.yml",
+            "/@evil.example/app.yml",
+        ] {
+            let url = resolver().resolve(path).unwrap();
+            assert_eq!(url.host_str(), Some("config.internal"));
+            assert_eq!(url.port(), Some(8888));
+            assert!(url.path().starts_with("/base/"), "{}", url);
+        }
+    }
+
+    #[test]
+    fn rejects_control_characters() {
+        for path in [
+            "/app%00.yml",
+            "/app%0a.yml",
+            "/app%0D%0A.yml",
+            "/app%7f.yml",
+            "/app\n.yml",
+            "/app\t.yml",
+        ] {
+            assert_rejected(path);
+        }
+    }
+
+    #[test]
+    fn does_not_smuggle_query_or_fragment_from_path() {
+        let url = resolver().resolve("/app%3Fprofile=prod.yml").unwrap();
+        assert_eq!(url.query(), None);
+        assert_eq!(url.path(), "/base/app%3Fprofile=prod.yml");
+
+        let url = resolver().resolve("/app%23fragment.yml").unwrap();
+        assert_eq!(url.fragment(), None);
+
+        let url = resolver().resolve("/app.yml?a=1#@evil.example/x").unwrap();
+        assert_eq!(url.host_str(), Some("config.internal"));
+        assert_eq!(url.path(), "/base/app.yml");
+        assert_eq!(url.fragment(), None);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::models::notifications::ChangeSource;
+use crate::services::cache_key::path_from_key;
+use crate::services::cache_service::CacheService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::offline_mode::OfflineMode;
+use crate::services::upstream::{UpstreamChange, UpstreamSet};
+use crate::utils::errors::AppResult;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// 接收支持监听的上游（Consul、etcd 等）发出的变化，
+/// 失效受影响的缓存键并通知订阅者重新拉取
+pub struct UpstreamWatcher {
+    upstreams: Arc<UpstreamSet>,
+    cache_service: Arc<CacheService>,
+    change_notifier: Arc<ChangeNotifier>,
+    offline: Arc<OfflineMode>,
+}
+
+impl UpstreamWatcher {
+    pub fn new(
+        upstreams: Arc<UpstreamSet>,
+        cache_service: Arc<CacheService>,
+        change_notifier: Arc<ChangeNotifier>,
+        offline: Arc<OfflineMode>,
+    ) -> Self {
+        Self {
+            upstreams,
+            cache_service,
+            change_notifier,
+            offline,
+        }
+    }
+
+    /// 启动所有上游的监听任务与处理变化的后台任务
+    pub fn start(self: &Arc<Self>) {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        for upstream in self.upstreams.upstreams() {
+            upstream.watch(sender.clone());
+        }
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = watcher.resume().await {
+                tracing::warn!("Failed to resume upstream watches from cache: {}", e);
+            }
+        });
+
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            while let Some(change) = receiver.recv().await {
+                if let Err(e) = watcher.invalidate(&change).await {
+                    tracing::warn!(
+                        "Failed to invalidate cache after change in {}: {}",
+                        change.upstream,
+                        e
+                    );
+                }
+            }
+        });
+    }
+
+    /// 按缓存恢复只在读取时登记监听的上游（Nacos），
+    /// 否则重启后直接命中缓存的配置在过期前收不到变化
+    async fn resume(&self) -> AppResult<()> {
+        let mut resumed = 0;
+        for key in self.cache_service.list_keys().await? {
+            let Some(path) = path_from_key(&key) else {
+                continue;
+            };
+            let upstream = self.upstreams.find(path);
+            if !upstream.listens_on_read() {
+                continue;
+            }
+            // 扫描与读取之间过期的条目直接跳过
+            let Some(body) = self.cache_service.get(&key).await? else {
+                continue;
+            };
+            upstream.resume(path, &body.decode()?);
+            resumed += 1;
+        }
+        if resumed > 0 {
+            tracing::info!("Resumed upstream watches for {} cache entries", resumed);
+        }
+        Ok(())
+    }
+
+    async fn invalidate(&self, change: &UpstreamChange) -> AppResult<()> {
+        // 离线模式冻结缓存，忽略上游变化
+        if self.offline.is_enabled() {
+            tracing::info!("Offline mode, ignoring change in {}", change.upstream);
+            return Ok(());
+        }
+
+        let affected: Vec<String> = self
+            .cache_service
+            .list_keys()
+            .await?
+            .into_iter()
+            .filter(|key| {
+                path_from_key(key).is_some_and(|path| {
+                    let upstream = self.upstreams.find(path);
+                    upstream.name() == change.upstream
+                        && (change.keys.is_empty()
+                            || change
+                                .keys
+                                .iter()
+                                .any(|changed| upstream.affects(path, changed)))
+                })
+            })
+            .collect();
+        if affected.is_empty() {
+            return Ok(());
+        }
+
+        self.cache_service.invalidate(&affected).await?;
+        tracing::info!(
+            "Change in {} invalidated {} cache entries",
+            change.upstream,
+            affected.len()
+        );
+
+        // 失效的条目内容未知，通知订阅者重新拉取
+        let paths: BTreeSet<&str> = affected
+            .iter()
+            .filter_map(|key| path_from_key(key))
+            .collect();
+        for path in paths {
+            if let Err(e) = self
+                .change_notifier
+                .publish(path, None, ChangeSource::Upstream)
+                .await
+            {
+                tracing::warn!("Failed to publish upstream change for {}: {}", path, e);
+            }
+        }
+        Ok(())
+    }
+}
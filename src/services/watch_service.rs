@@ -0,0 +1,207 @@
+use crate::models::notifications::{WatchItem, WatchRequest};
+use crate::services::cache_service::CacheService;
+use crate::services::change_notifier::ChangeNotifier;
+use crate::services::override_service::OverrideService;
+use crate::services::proxy_service::ProxyService;
+use crate::utils::errors::{AppError, AppResult};
+use axum::http::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+/// 一个被监听的配置文件
+struct WatchTarget {
+    /// 客户端请求的原始路径与持有的哈希
+    item: WatchItem,
+    canonical: String,
+    cache_key: String,
+}
+
+/// 长轮询监听：对比客户端持有的版本与刷新时记录的内容哈希，
+/// 没有差异时等待变更事件或超时
+pub struct WatchService {
+    proxy_service: Arc<ProxyService>,
+    cache_service: Arc<CacheService>,
+    override_service: Arc<OverrideService>,
+    change_notifier: Arc<ChangeNotifier>,
+}
+
+impl WatchService {
+    pub fn new(
+        proxy_service: Arc<ProxyService>,
+        cache_service: Arc<CacheService>,
+        override_service: Arc<OverrideService>,
+        change_notifier: Arc<ChangeNotifier>,
+    ) -> Self {
+        Self {
+            proxy_service,
+            cache_service,
+            override_service,
+            change_notifier,
+        }
+    }
+
+    /// 返回版本与客户端不一致的配置文件，超时仍无变化时返回空列表
+    pub async fn watch(
+        &self,
+        request: WatchRequest,
+        headers: &HeaderMap,
+    ) -> AppResult<Vec<WatchItem>> {
+        let config = self.change_notifier.config();
+        if !config.enabled {
+            return Err(AppError::NotFound(
+                "Change notifications are disabled".to_string(),
+            ));
+        }
+        if request.items.is_empty() {
+            return Err(AppError::BadRequest("items is required".to_string()));
+        }
+        if request.items.len() > config.max_watch_items {
+            return Err(AppError::BadRequest(format!(
+                "At most {} items can be watched",
+                config.max_watch_items
+            )));
+        }
+
+        let timeout = Duration::from_secs(
+            request
+                .timeout_secs
+                .unwrap_or(config.long_poll_timeout_secs)
+                .min(config.max_long_poll_timeout_secs),
+        );
+        let targets = request
+            .items
+            .into_iter()
+            .map(|item| {
+                Ok(WatchTarget {
+                    canonical: self.proxy_service.canonical_path_for(&item.path)?,
+                    cache_key: self.proxy_service.cache_key_for(&item.path, headers)?,
+                    item,
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        // 先订阅再检查当前版本，避免检查与等待之间的变化被遗漏
+        let mut receiver = self.change_notifier.subscribe();
+        let changed = self.changed(targets.iter()).await?;
+        if !changed.is_empty() {
+            return Ok(changed);
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let recheck: Vec<&WatchTarget> =
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Err(_) => return Ok(Vec::new()),
+                    Ok(Ok(event)) => targets
+                        .iter()
+                        .filter(|target| target.canonical == event.path)
+                        .collect(),
+                    // 缓冲区溢出时无法确定错过了哪些事件，全部重新检查
+                    Ok(Err(RecvError::Lagged(_))) => targets.iter().collect(),
+                    Ok(Err(RecvError::Closed)) => return Ok(Vec::new()),
+                };
+
+            // 事件按规范路径发布，vary 不同的缓存键需要各自重新读取哈希
+            let changed = self.changed(recheck.into_iter()).await?;
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+
+    async fn changed(
+        &self,
+        targets: impl Iterator<Item = &WatchTarget>,
+    ) -> AppResult<Vec<WatchItem>> {
+        let mut changed = Vec::new();
+        for target in targets {
+            let current = self.current_hash(target).await?;
+            if let Some(item) = changed_item(&target.item, current) {
+                changed.push(item);
+            }
+        }
+        Ok(changed)
+    }
+
+    async fn current_hash(&self, target: &WatchTarget) -> AppResult<Option<String>> {
+        let overridden = self
+            .override_service
+            .get_info(&target.canonical)
+            .await?
+            .map(|info| info.hash);
+        let pinned = self.cache_service.pinned_hash(&target.cache_key).await?;
+        let refreshed = self.change_notifier.current_hash(&target.cache_key).await?;
+        Ok(effective_hash(overridden, pinned, refreshed))
+    }
+}
+
+/// 与代理返回的内容保持一致：生效中的紧急覆盖优先，其次是固定的历史版本，最后是刷新记录的哈希
+fn effective_hash(
+    overridden: Option<String>,
+    pinned: Option<String>,
+    refreshed: Option<String>,
+) -> Option<String> {
+    overridden.or(pinned).or(refreshed)
+}
+
+/// 当前哈希已知且与客户端持有的不同时返回变化项
+fn changed_item(item: &WatchItem, current: Option<String>) -> Option<WatchItem> {
+    (current.is_some() && current != item.hash).then(|| WatchItem {
+        path: item.path.clone(),
+        hash: current,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(hash: &str) -> WatchItem {
+        WatchItem {
+            path: "/app-prod.yml".to_string(),
+            hash: Some(hash.to_string()),
+        }
+    }
+
+    fn hash(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn pinned_key_is_unchanged_for_clients_holding_the_pinned_hash() {
+        // 上游已刷新到新版本，但键被固定到旧版本，代理返回的仍是固定版本
+        let current = effective_hash(None, hash("pinned"), hash("refreshed"));
+        assert!(changed_item(&held("pinned"), current.clone()).is_none());
+
+        let changed = changed_item(&held("refreshed"), current).unwrap();
+        assert_eq!(changed.hash.as_deref(), Some("pinned"));
+
+        // 解除固定后回到刷新记录的哈希
+        let current = effective_hash(None, None, hash("refreshed"));
+        assert!(changed_item(&held("pinned"), current).is_some());
+    }
+
+    #[test]
+    fn override_takes_precedence_over_pin() {
+        let current = effective_hash(hash("override"), hash("pinned"), hash("refreshed"));
+        assert_eq!(current.as_deref(), Some("override"));
+        assert!(changed_item(&held("pinned"), current).is_some());
+    }
+
+    #[test]
+    fn reports_only_known_different_hashes() {
+        assert!(changed_item(&held("aaaa"), None).is_none());
+        assert!(changed_item(&held("aaaa"), hash("aaaa")).is_none());
+        let changed = changed_item(&held("aaaa"), hash("bbbb")).unwrap();
+        assert_eq!(changed.path, "/app-prod.yml");
+        assert_eq!(changed.hash.as_deref(), Some("bbbb"));
+
+        let unknown = WatchItem {
+            path: "/app-prod.yml".to_string(),
+            hash: None,
+        };
+        assert!(changed_item(&unknown, hash("aaaa")).is_some());
+    }
+}
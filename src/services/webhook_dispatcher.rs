@@ -0,0 +1,207 @@
+use crate::models::history::ChangeKind;
+use crate::models::notifications::{ConfigChangedEvent, DiffSummary};
+use crate::services::cache_service::CacheService;
+use crate::services::history_service::structural_changes;
+use crate::system::OutboundWebhookConfig;
+use crate::utils::backoff::backoff_delay;
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use globset::{Glob, GlobMatcher};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode, header};
+use sha2::Sha256;
+use std::sync::Arc;
+use url::Url;
+
+// 差异摘要中最多列出的配置键数量
+const MAX_SUMMARY_KEYS: usize = 20;
+// 携带载荷签名的请求头
+const SIGNATURE_HEADER: &str = "x-signature-256";
+
+struct WebhookTarget {
+    url: Url,
+    matchers: Vec<GlobMatcher>,
+    config: OutboundWebhookConfig,
+}
+
+impl WebhookTarget {
+    fn matches(&self, path: &str) -> bool {
+        self.matchers.is_empty() || self.matchers.iter().any(|m| m.is_match(path))
+    }
+}
+
+/// 配置内容变化时向外部地址推送事件，在后台发送并按退避策略重试，不阻塞代理请求
+pub struct WebhookDispatcher {
+    client: Client,
+    cache_service: Arc<CacheService>,
+    targets: Arc<Vec<WebhookTarget>>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        configs: &[OutboundWebhookConfig],
+        client: Client,
+        cache_service: Arc<CacheService>,
+    ) -> AppResult<Self> {
+        let targets = configs
+            .iter()
+            .map(|config| {
+                let url = Url::parse(&config.url).map_err(|e| {
+                    AppError::Config(format!("Invalid webhook URL '{}': {}", config.url, e))
+                })?;
+                let matchers = config
+                    .paths
+                    .iter()
+                    .map(|pattern| {
+                        Glob::new(pattern)
+                            .map(|glob| glob.compile_matcher())
+                            .map_err(|e| {
+                                AppError::Config(format!(
+                                    "Invalid webhook path pattern '{}': {}",
+                                    pattern, e
+                                ))
+                            })
+                    })
+                    .collect::<AppResult<Vec<_>>>()?;
+                Ok(WebhookTarget {
+                    url,
+                    matchers,
+                    config: config.clone(),
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(Self {
+            client,
+            cache_service,
+            targets: Arc::new(targets),
+        })
+    }
+
+    /// 推送变更事件：先从版本历史读取旧内容生成差异摘要，再发送给所有匹配路径的地址
+    pub fn notify(&self, mut event: ConfigChangedEvent, new_body: Bytes) {
+        let file_path = event.path.split('?').next().unwrap_or_default();
+        let matched: Vec<usize> = self
+            .targets
+            .iter()
+            .enumerate()
+            .filter(|(_, target)| target.matches(file_path))
+            .map(|(index, _)| index)
+            .collect();
+        if matched.is_empty() {
+            return;
+        }
+
+        let client = self.client.clone();
+        let cache_service = self.cache_service.clone();
+        let targets = self.targets.clone();
+        tokio::spawn(async move {
+            event.diff = diff_summary(&cache_service, &event, &new_body).await;
+            let payload = match serde_json::to_vec(&event) {
+                Ok(payload) => Bytes::from(payload),
+                Err(e) => {
+                    tracing::warn!("Failed to encode webhook event for {}: {}", event.path, e);
+                    return;
+                }
+            };
+
+            for index in matched {
+                let client = client.clone();
+                let targets = targets.clone();
+                let payload = payload.clone();
+                tokio::spawn(async move { deliver(&client, &targets[index], payload).await });
+            }
+        });
+    }
+}
+
+async fn diff_summary(
+    cache_service: &CacheService,
+    event: &ConfigChangedEvent,
+    new_body: &[u8],
+) -> Option<DiffSummary> {
+    let old_body = match cache_service.get_version(&event.key, &event.old_hash).await {
+        Ok(Some(body)) => body.decode().ok()?,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load previous version of {}: {}", event.key, e);
+            return None;
+        }
+    };
+    let changes = structural_changes(&old_body, new_body).ok()?;
+
+    let count = |kind: ChangeKind| changes.iter().filter(|c| c.change == kind).count();
+    Some(DiffSummary {
+        added: count(ChangeKind::Added),
+        removed: count(ChangeKind::Removed),
+        changed: count(ChangeKind::Changed),
+        keys: changes
+            .iter()
+            .take(MAX_SUMMARY_KEYS)
+            .map(|change| change.key.clone())
+            .collect(),
+    })
+}
+
+/// 发送事件，连接错误、超时、429 与 5xx 按退避策略重试
+async fn deliver(client: &Client, target: &WebhookTarget, payload: Bytes) {
+    let retry = &target.config.retry;
+    let signature = target.config.secret.as_deref().map(|secret| {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(&payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    });
+
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(target.url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(payload.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let failure = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) if !is_retryable_status(response.status()) => {
+                tracing::warn!(
+                    "Webhook {} rejected config change event with status {}",
+                    target.url,
+                    response.status()
+                );
+                return;
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt >= retry.max_retries {
+            tracing::warn!(
+                "Giving up on webhook {} after {} attempts: {}",
+                target.url,
+                attempt + 1,
+                failure
+            );
+            return;
+        }
+        let delay = backoff_delay(retry, attempt);
+        attempt += 1;
+        tracing::warn!(
+            "Webhook {} failed ({}), retry {}/{} in {:?}",
+            target.url,
+            failure,
+            attempt,
+            retry.max_retries,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
@@ -0,0 +1,46 @@
+use crate::system::AppState;
+use crate::utils::errors::AppError;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use subtle::ConstantTimeEq;
+
+/// 管理接口鉴权提取器：校验 `Authorization: Bearer <token>`，
+/// 未配置 `admin.token` 时拒绝所有管理请求
+pub struct AdminAuth;
+
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(expected) = state
+            .config
+            .admin
+            .token
+            .as_deref()
+            .filter(|t| !t.is_empty())
+        else {
+            return Err(AppError::Forbidden(
+                "Admin API is disabled, set admin.token to enable it".to_string(),
+            ));
+        };
+
+        let provided = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| AppError::Unauthorized("Missing bearer token".to_string()))?;
+
+        if bool::from(provided.as_bytes().ct_eq(expected.as_bytes())) {
+            Ok(AdminAuth)
+        } else {
+            Err(AppError::Unauthorized("Invalid bearer token".to_string()))
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use std::fs;
 
-use crate::utils::errors::{AppError, AppResult};
+use crate::utils::{
+    compression::Compression,
+    errors::{AppError, AppResult},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,6 +19,79 @@ pub struct SystemConfig {
 
     // Redis 配置
     pub redis: RedisConfig,
+
+    // 请求头转发配置
+    #[serde(default)]
+    pub headers: HeaderConfig,
+
+    // 缓存键规范化配置
+    #[serde(default)]
+    pub cache_key: CacheKeyConfig,
+
+    // 上游 HTTP 客户端配置
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+
+    // 熔断器配置
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    // 上游并发隔离配置
+    #[serde(default)]
+    pub bulkhead: BulkheadConfig,
+
+    // 上游响应体大小限制
+    #[serde(default)]
+    pub body_limit: BodyLimitConfig,
+
+    // 上游内容校验配置
+    #[serde(default)]
+    pub validation: ValidationConfig,
+
+    // {cipher} 值解密配置
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    // 按路径匹配的路由规则，按顺序取第一条匹配项
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+
+    // 本地补丁覆盖层，按顺序应用所有匹配项
+    #[serde(default)]
+    pub overlays: Vec<OverlayConfig>,
+
+    // 配置变更通知
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+
+    // 配置版本历史
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    // 管理接口配置
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    // Git 推送回调配置
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+
+    // 配置内容变化时回调的外部地址
+    #[serde(default)]
+    pub outbound_webhooks: Vec<OutboundWebhookConfig>,
+
+    // 本地磁盘快照，Redis 与上游同时不可用时兜底
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+
+    // 离线（只读）模式：只从缓存与快照返回、不访问上游，缓存条目不再过期。
+    // 管理员通过 /admin/offline 切换后以切换结果为准
+    #[serde(default)]
+    pub offline: bool,
+
+    // 上游流量录制与回放，用于无法访问配置中心的本地开发
+    #[serde(default)]
+    pub recording: RecordingConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,6 +104,580 @@ pub struct RedisConfig {
     pub password: String,
     // 缓存过期时间
     pub cache_expire_time: u64,
+    // 过期副本保留时间，上游不可用时用于兜底
+    #[serde(default = "default_stale_expire_time")]
+    pub stale_expire_time: u64,
+    // 缓存值压缩配置
+    #[serde(default)]
+    pub compression: CompressionConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CompressionConfig {
+    // 压缩算法：none / zstd / gzip
+    pub algorithm: Compression,
+    // 压缩级别
+    pub level: i32,
+    // 小于该大小（字节）的值不压缩
+    pub min_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: Compression::None,
+            level: 3,
+            min_size: 1024,
+        }
+    }
+}
+
+fn default_stale_expire_time() -> u64 {
+    7 * 24 * 3600
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HeaderConfig {
+    // 需要转发到上游的客户端请求头
+    #[serde(default)]
+    pub forward: Vec<String>,
+    // 参与缓存键计算的请求头，取值哈希后拼入缓存键
+    #[serde(default)]
+    pub vary: Vec<String>,
+}
+
+impl HeaderConfig {
+    /// 转发到上游的请求头会影响响应内容，必须同时参与缓存键计算，
+    /// 否则不同租户的响应会共用同一个缓存键
+    fn validate(&self) -> AppResult<()> {
+        let unvaried: Vec<&str> = self
+            .forward
+            .iter()
+            .filter(|forward| {
+                !self
+                    .vary
+                    .iter()
+                    .any(|vary| vary.eq_ignore_ascii_case(forward))
+            })
+            .map(String::as_str)
+            .collect();
+        if unvaried.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Config(format!(
+                "Forwarded headers must also be listed in headers.vary: {}",
+                unvaried.join(", ")
+            )))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheKeyConfig {
+    // 计算缓存键时忽略的查询参数（如缓存破坏参数），不区分大小写
+    #[serde(default = "default_ignored_params")]
+    pub ignored_params: Vec<String>,
+    // vary 请求头取值的 HMAC-SHA256 密钥，配置了 headers.vary 时必填，所有副本须一致
+    #[serde(default)]
+    pub vary_secret: Option<String>,
+}
+
+impl Default for CacheKeyConfig {
+    fn default() -> Self {
+        Self {
+            ignored_params: default_ignored_params(),
+            vary_secret: None,
+        }
+    }
+}
+
+fn default_ignored_params() -> Vec<String> {
+    vec!["_".to_string()]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpClientConfig {
+    // 建立连接超时（毫秒）
+    pub connect_timeout_ms: u64,
+    // 读取超时（毫秒），两次读取之间的最大间隔
+    pub read_timeout_ms: u64,
+    // 单次请求总超时（毫秒）
+    pub request_timeout_ms: u64,
+    // 空闲连接保留时间（秒）
+    pub pool_idle_timeout_secs: u64,
+    // 每个主机保留的最大空闲连接数
+    pub pool_max_idle_per_host: usize,
+    // 重试配置
+    pub retry: RetryConfig,
+    // 出站 HTTP 代理地址
+    pub proxy: Option<String>,
+    // 自定义 CA 证书（PEM）路径
+    pub ca_cert_path: Option<String>,
+    // mTLS 客户端证书（PEM）路径
+    pub client_cert_path: Option<String>,
+    // mTLS 客户端私钥（PEM）路径
+    pub client_key_path: Option<String>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 3_000,
+            read_timeout_ms: 10_000,
+            request_timeout_ms: 30_000,
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: 32,
+            retry: RetryConfig::default(),
+            proxy: None,
+            ca_cert_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryConfig {
+    // 最大重试次数（不含首次请求）
+    pub max_retries: u32,
+    // 首次重试前的退避时间（毫秒）
+    pub initial_backoff_ms: u64,
+    // 退避时间上限（毫秒）
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 3_000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    // 是否启用熔断器
+    pub enabled: bool,
+    // 统计失败率的滑动窗口大小（请求数）
+    pub window_size: usize,
+    // 计算失败率所需的最少请求数
+    pub minimum_calls: usize,
+    // 触发熔断的失败率阈值（0~1）
+    pub failure_rate_threshold: f64,
+    // 熔断打开后的冷却时间（秒）
+    pub open_duration_secs: u64,
+    // 半开状态下允许的试探请求数
+    pub half_open_max_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_size: 20,
+            minimum_calls: 10,
+            failure_rate_threshold: 0.5,
+            open_duration_secs: 30,
+            half_open_max_calls: 3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BulkheadConfig {
+    // 全局最大并发上游请求数
+    pub max_concurrent: usize,
+    // 单个上游最大并发请求数
+    pub max_concurrent_per_upstream: usize,
+    // 最大排队请求数，超出直接拒绝
+    pub max_queue: usize,
+    // 排队等待超时（毫秒）
+    pub queue_timeout_ms: u64,
+}
+
+impl Default for BulkheadConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 64,
+            max_concurrent_per_upstream: 32,
+            max_queue: 256,
+            queue_timeout_ms: 2_000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BodyLimitConfig {
+    // 允许缓存的最大响应体大小（字节）
+    pub max_body_size: u64,
+    // 超出限制时的处理策略
+    pub oversize_policy: OversizePolicy,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_body_size: 10 * 1024 * 1024,
+            oversize_policy: OversizePolicy::Reject,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizePolicy {
+    // 拒绝请求
+    Reject,
+    // 直接流式转发给客户端，不写入缓存
+    Stream,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ValidationConfig {
+    // 是否在缓存前校验上游内容
+    pub enabled: bool,
+    // 直接拒绝的上游 Content-Type
+    pub rejected_content_types: Vec<String>,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rejected_content_types: vec!["text/html".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    // 对称密钥，对应 Spring Cloud Config 的 encrypt.key
+    pub key: Option<String>,
+    // 对称密钥的盐（十六进制）
+    pub salt: String,
+    // RSA 私钥 PEM 文件路径，与对称密钥同时配置时按密文格式选择：十六进制为对称密钥，Base64 为 RSA
+    pub rsa_private_key_path: Option<String>,
+    // RSA 模式下 AES 密钥的盐（十六进制）
+    pub rsa_salt: String,
+    // RSA 是否使用 OAEP 填充，否则使用 PKCS#1 v1.5
+    pub rsa_oaep: bool,
+    // RSA 模式下是否使用 AES-GCM
+    pub rsa_strong: bool,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            salt: "deadbeef".to_string(),
+            rsa_private_key_path: None,
+            rsa_salt: "deadbeef".to_string(),
+            rsa_oaep: false,
+            rsa_strong: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RouteConfig {
+    // 路径 glob 模式，如 `/app/**/*.yml`
+    pub pattern: String,
+    // 是否解密 {cipher} 值
+    #[serde(default)]
+    pub decrypt: bool,
+    // 是否允许把解密后的内容写入 Redis，默认只缓存密文
+    #[serde(default)]
+    pub cache_decrypted: bool,
+    // 该路由使用的上游，默认使用 proxy_address 指向的配置中心
+    #[serde(default)]
+    pub upstream: Option<UpstreamConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UpstreamConfig {
+    // Spring 配置中心 HTTP 接口
+    Http {
+        // 上游地址
+        base_url: String,
+    },
+    // 本地目录，按 Spring 的规则查找并合并 {application}-{profile}.yml
+    Directory {
+        // 配置文件所在目录
+        path: String,
+    },
+    // 本地 Git 仓库，按标签（分支、标签或提交）读取文件，规则同本地目录
+    Git {
+        // 仓库路径，可以是工作区或裸仓库
+        repository: String,
+        // 请求路径未携带标签时使用的分支
+        #[serde(default = "default_git_label")]
+        default_label: String,
+    },
+    // Consul KV，请求路径去掉路由前缀后映射为同名的键或以路径（去掉扩展名）为前缀的键树，
+    // 通过阻塞查询监听变化并失效缓存
+    Consul {
+        // Consul 地址
+        address: String,
+        // 键前缀
+        #[serde(default = "default_kv_prefix")]
+        prefix: String,
+        // ACL Token
+        token: Option<String>,
+        // 数据中心，默认使用所连接 agent 的数据中心
+        datacenter: Option<String>,
+        // 阻塞查询的最长等待时间（秒）
+        #[serde(default = "default_kv_wait_secs")]
+        wait_secs: u64,
+    },
+    // etcd v3，通过 gRPC 网关的 JSON 接口访问，路径映射规则同 Consul，通过 watch 监听变化
+    Etcd {
+        // etcd 地址
+        address: String,
+        // 键前缀
+        #[serde(default = "default_kv_prefix")]
+        prefix: String,
+        // 监听连接收不到进度通知的最长时间（秒），超时后重新连接。
+        // etcd 默认每 10 分钟发送一次进度通知
+        #[serde(default = "default_etcd_progress_timeout_secs")]
+        progress_timeout_secs: u64,
+    },
+    // Nacos 配置中心（open API），请求路径 /{dataId} 或 /{group}/{dataId}，
+    // dataId 按原样读取与返回，通过监听接口长轮询配置变化并失效缓存
+    Nacos(NacosConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NacosConfig {
+    // Nacos 地址，包含上下文路径，如 http://127.0.0.1:8848/nacos
+    pub address: String,
+    // 命名空间 ID，默认使用 public 命名空间
+    pub namespace: Option<String>,
+    // 请求路径未携带分组时使用的分组
+    #[serde(default = "default_nacos_group")]
+    pub group: String,
+    // 开启鉴权时的用户名
+    pub username: Option<String>,
+    // 开启鉴权时的密码
+    pub password: Option<String>,
+    // 监听长轮询的超时时间（秒）
+    #[serde(default = "default_nacos_long_poll_secs")]
+    pub long_poll_secs: u64,
+}
+
+fn default_git_label() -> String {
+    "main".to_string()
+}
+
+fn default_kv_prefix() -> String {
+    "config/".to_string()
+}
+
+fn default_kv_wait_secs() -> u64 {
+    300
+}
+
+fn default_etcd_progress_timeout_secs() -> u64 {
+    900
+}
+
+fn default_nacos_group() -> String {
+    "DEFAULT_GROUP".to_string()
+}
+
+fn default_nacos_long_poll_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlayConfig {
+    // 路径 glob 模式
+    pub pattern: String,
+    // 补丁文件路径（YAML 或 JSON）
+    pub file: String,
+    // 合并策略
+    #[serde(default)]
+    pub strategy: OverlayStrategy,
+    // 响应头中显示的来源名称，默认使用文件名
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayStrategy {
+    // YAML 深度合并，补丁中的空值不会覆盖已有值
+    #[default]
+    Merge,
+    // RFC 7396 JSON Merge Patch，空值删除对应键
+    MergePatch,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotificationConfig {
+    // 是否启用变更通知
+    pub enabled: bool,
+    // 副本间广播变更事件的 Redis 频道
+    pub channel: String,
+    // 本地事件缓冲区大小，订阅者落后超过该数量时收到 lagged 事件
+    pub buffer_size: usize,
+    // SSE 心跳间隔（秒）
+    pub keep_alive_secs: u64,
+    // 长轮询默认等待时间（秒）
+    pub long_poll_timeout_secs: u64,
+    // 长轮询允许的最长等待时间（秒）
+    pub max_long_poll_timeout_secs: u64,
+    // 单次长轮询最多监听的路径数
+    pub max_watch_items: usize,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            channel: "config_changes".to_string(),
+            buffer_size: 1024,
+            keep_alive_secs: 15,
+            long_poll_timeout_secs: 30,
+            max_long_poll_timeout_secs: 90,
+            max_watch_items: 100,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    // 是否记录配置版本历史
+    pub enabled: bool,
+    // 每个缓存键保留的最大版本数
+    pub max_versions: usize,
+    // 固定到历史版本的最长时间（秒）
+    pub max_pin_duration_secs: u64,
+    // 版本历史的保留时间（秒），每次写入缓存时刷新
+    pub expire_time: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_versions: 10,
+            max_pin_duration_secs: 7 * 24 * 3600,
+            expire_time: 30 * 24 * 3600,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AdminConfig {
+    // 管理接口的 Bearer 令牌，未配置时管理接口全部禁用
+    pub token: Option<String>,
+    // 审计日志保留的最大条数
+    pub audit_log_size: usize,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            audit_log_size: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct WebhookConfig {
+    // GitHub/Gitea 的签名密钥，也是 GitLab 的 Secret token；未配置时拒绝所有回调
+    pub secret: Option<String>,
+    // 是否在失效后立即从上游重新拉取，否则等待下一次请求时再拉取
+    pub refresh: bool,
+    // 请求路径中用作标签（`/{label}/{application}-{profile}.yml`）的分支或标签名。
+    // 只有路径首段是其中之一且与推送的分支不同时才跳过，其余情况一律失效
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboundWebhookConfig {
+    // 接收变更事件的地址
+    pub url: String,
+    // 路径 glob 模式，为空时所有配置文件的变更都会发送
+    #[serde(default)]
+    pub paths: Vec<String>,
+    // HMAC-SHA256 签名密钥，配置后通过 X-Signature-256 请求头携带签名
+    pub secret: Option<String>,
+    // 发送失败时的重试策略
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    // 是否启用磁盘快照
+    pub enabled: bool,
+    // 快照目录
+    pub directory: String,
+    // 快照写入间隔（秒）
+    pub interval_secs: u64,
+    // Redis 中已不存在的条目在快照中继续保留的时间（秒）
+    pub retention_secs: u64,
+    // 启动时用快照补齐 Redis 中缺失的过期副本
+    pub seed_on_startup: bool,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "./snapshot".to_string(),
+            interval_secs: 300,
+            retention_secs: 604800,
+            seed_on_startup: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RecordingConfig {
+    // 录制模式
+    pub mode: RecordingMode,
+    // 录制文件目录
+    pub directory: String,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            mode: RecordingMode::Off,
+            directory: "./recordings".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingMode {
+    // 正常访问上游
+    #[default]
+    Off,
+    // 访问上游并把每个响应写入录制目录
+    Record,
+    // 只从录制文件返回，不访问上游
+    Replay,
 }
 
 impl SystemConfig {
@@ -39,6 +689,7 @@ impl SystemConfig {
         let config: SystemConfig = serde_yml::from_str(&config_str).map_err(|e| {
             AppError::Config(format!("Failed to parse config file '{}': {}", path, e))
         })?;
+        config.headers.validate()?;
 
         Ok(config)
     }
@@ -0,0 +1,86 @@
+use std::{fs, time::Duration};
+
+use reqwest::{Certificate, Client, ClientBuilder, Identity, Proxy};
+
+use crate::{
+    system::HttpClientConfig,
+    utils::errors::{AppError, AppResult},
+};
+
+/// 根据配置构建上游 HTTP 客户端
+pub fn build_http_client(config: &HttpClientConfig) -> AppResult<Client> {
+    client_builder(config)?
+        .read_timeout(Duration::from_millis(config.read_timeout_ms))
+        .timeout(Duration::from_millis(config.request_timeout_ms))
+        .build()
+        .map_err(|e| AppError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// 构建用于长轮询与监听流的客户端：代理与证书配置相同，但不设置读取与请求超时，
+/// 由调用方按请求指定超时
+pub fn build_watch_client(config: &HttpClientConfig) -> AppResult<Client> {
+    client_builder(config)?
+        .build()
+        .map_err(|e| AppError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// 构建推送外部 Webhook 的客户端：接收方不是上游，不使用上游的出站代理、CA 证书与 mTLS 身份，
+/// 只沿用连接与请求超时
+pub fn build_webhook_client(config: &HttpClientConfig) -> AppResult<Client> {
+    Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .timeout(Duration::from_millis(config.request_timeout_ms))
+        .build()
+        .map_err(|e| AppError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+fn client_builder(config: &HttpClientConfig) -> AppResult<ClientBuilder> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host);
+
+    // 出站代理
+    if let Some(proxy_url) = &config.proxy {
+        let proxy = Proxy::all(proxy_url).map_err(|e| {
+            AppError::Config(format!("Invalid outbound proxy '{}': {}", proxy_url, e))
+        })?;
+        builder = builder.proxy(proxy);
+    }
+
+    // 自定义 CA 证书
+    if let Some(ca_path) = &config.ca_cert_path {
+        let pem = read_pem(ca_path)?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| AppError::Config(format!("Invalid CA bundle '{}': {}", ca_path, e)))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    // mTLS 客户端证书
+    match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let mut pem = read_pem(cert_path)?;
+            pem.push(b'\n');
+            pem.extend(read_pem(key_path)?);
+            let identity = Identity::from_pem(&pem).map_err(|e| {
+                AppError::Config(format!("Invalid client certificate '{}': {}", cert_path, e))
+            })?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(AppError::Config(
+                "client_cert_path and client_key_path must be configured together".to_string(),
+            ));
+        }
+    }
+
+    Ok(builder)
+}
+
+fn read_pem(path: &str) -> AppResult<Vec<u8>> {
+    fs::read(path)
+        .map_err(|e| AppError::Config(format!("Failed to read PEM file '{}': {}", path, e)))
+}
@@ -1,9 +1,19 @@
+mod admin_auth;
 mod config;
+mod http_client;
 mod route_register;
 mod shutdown;
 mod state;
 
-pub use config::{RedisConfig, SystemConfig};
+pub use admin_auth::AdminAuth;
+pub use config::{
+    AdminConfig, BodyLimitConfig, BulkheadConfig, CacheKeyConfig, CircuitBreakerConfig,
+    CompressionConfig, EncryptionConfig, HeaderConfig, HistoryConfig, HttpClientConfig,
+    NacosConfig, NotificationConfig, OutboundWebhookConfig, OverlayConfig, OverlayStrategy,
+    OversizePolicy, RecordingConfig, RecordingMode, RedisConfig, RetryConfig, RouteConfig,
+    SnapshotConfig, SystemConfig, UpstreamConfig, ValidationConfig, WebhookConfig,
+};
+pub use http_client::{build_http_client, build_watch_client, build_webhook_client};
 pub use route_register::create_router;
 pub use shutdown::{cleanup_resources, shutdown_signal};
 pub use state::AppState;
@@ -1,12 +1,32 @@
 use axum::Router;
 use axum::routing::method_routing::*;
-use utoipa::OpenApi;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::handlers::cache::clear_cache;
+use crate::handlers::events::{subscribe_changes, watch_changes};
 use crate::handlers::health::get_runtime;
+use crate::handlers::history::{
+    diff_versions, inspect_cache, list_pins, list_versions, pin_version, unpin_version,
+};
+use crate::handlers::monitor::receive_push;
+use crate::handlers::offline::{get_offline, set_offline};
+use crate::handlers::overrides::{delete_override, list_audit, list_overrides, put_override};
 use crate::handlers::proxy::{home_page, proxy_config_center};
-use crate::models::{responses::ClearCacheResponse, runtime::RuntimeInfo};
+use crate::models::{
+    history::{
+        CacheEntryInfo, ChangeKind, ConfigVersion, PinInfo, PinRequest, PropertyChange,
+        StructuralDiff, UnpinResponse, VersionListResponse,
+    },
+    notifications::{
+        ChangeEvent, ChangeSource, ConfigChangedEvent, DiffSummary, WatchItem, WatchRequest,
+        WatchResponse,
+    },
+    overrides::{AuditAction, AuditEntry, OverrideInfo, OverrideRequest, RemoveOverrideResponse},
+    responses::{ClearCacheResponse, MonitorResponse},
+    runtime::{CircuitBreakerInfo, CircuitState, OfflineRequest, OfflineStatus, RuntimeInfo},
+};
 use crate::system::AppState;
 use crate::utils::errors::ErrorResponse;
 
@@ -24,19 +44,80 @@ use crate::utils::errors::ErrorResponse;
     paths(
         crate::handlers::health::get_runtime,
         crate::handlers::cache::clear_cache,
-        crate::handlers::proxy::proxy_config_center
+        crate::handlers::monitor::receive_push,
+        crate::handlers::proxy::proxy_config_center,
+        crate::handlers::events::subscribe_changes,
+        crate::handlers::events::watch_changes,
+        crate::handlers::history::list_versions,
+        crate::handlers::history::diff_versions,
+        crate::handlers::history::inspect_cache,
+        crate::handlers::history::list_pins,
+        crate::handlers::history::pin_version,
+        crate::handlers::history::unpin_version,
+        crate::handlers::overrides::put_override,
+        crate::handlers::overrides::delete_override,
+        crate::handlers::overrides::list_overrides,
+        crate::handlers::overrides::list_audit,
+        crate::handlers::offline::get_offline,
+        crate::handlers::offline::set_offline
     ),
     components(
-        schemas(RuntimeInfo, ClearCacheResponse, ErrorResponse)
+        schemas(
+            RuntimeInfo,
+            CircuitBreakerInfo,
+            CircuitState,
+            ClearCacheResponse,
+            MonitorResponse,
+            ErrorResponse,
+            ConfigVersion,
+            VersionListResponse,
+            ChangeKind,
+            PropertyChange,
+            StructuralDiff,
+            CacheEntryInfo,
+            PinInfo,
+            PinRequest,
+            UnpinResponse,
+            OverrideRequest,
+            OverrideInfo,
+            RemoveOverrideResponse,
+            AuditAction,
+            AuditEntry,
+            OfflineRequest,
+            OfflineStatus,
+            ChangeEvent,
+            ChangeSource,
+            ConfigChangedEvent,
+            DiffSummary,
+            WatchItem,
+            WatchRequest,
+            WatchResponse
+        )
     ),
+    modifiers(&BearerSecurity),
     tags(
         (name = "monitoring", description = "监控和统计相关接口"),
         (name = "cache", description = "缓存管理相关接口"),
-        (name = "proxy", description = "反向代理相关接口")
+        (name = "proxy", description = "反向代理相关接口"),
+        (name = "admin", description = "管理接口，需要 Bearer 令牌")
     )
 )]
 pub struct ApiDoc;
 
+/// 注册管理接口使用的 Bearer 鉴权方案
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
 pub fn create_router(app_state: AppState) -> Router {
     let home_file_path = app_state.config.home_file_path.clone();
 
@@ -47,6 +128,24 @@ pub fn create_router(app_state: AppState) -> Router {
         )
         .route("/get-runtime", get(get_runtime))
         .route("/clear-cache", delete(clear_cache))
+        .route("/monitor", post(receive_push))
+        .route("/admin/history", get(list_versions))
+        .route("/admin/history/diff", get(diff_versions))
+        .route("/admin/cache", get(inspect_cache))
+        .route(
+            "/admin/pins",
+            get(list_pins).post(pin_version).delete(unpin_version),
+        )
+        .route(
+            "/admin/overrides",
+            get(list_overrides)
+                .put(put_override)
+                .delete(delete_override),
+        )
+        .route("/admin/audit", get(list_audit))
+        .route("/admin/offline", get(get_offline).put(set_offline))
+        .route("/events", get(subscribe_changes))
+        .route("/watch", post(watch_changes))
         .route("/{*all}", get(proxy_config_center))
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .with_state(app_state)
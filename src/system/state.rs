@@ -2,12 +2,17 @@ use std::sync::Arc;
 
 use crate::{
     models::runtime::RuntimeStats,
-    repositories::redis_repository::RedisRepository,
-    services::{cache_service::CacheService, proxy_service::ProxyService},
-    system::SystemConfig,
+    repositories::{redis_repository::RedisRepository, snapshot_repository::SnapshotRepository},
+    services::{
+        audit_service::AuditService, cache_service::CacheService, change_notifier::ChangeNotifier,
+        history_service::HistoryService, monitor_service::MonitorService,
+        offline_mode::OfflineMode, override_service::OverrideService, proxy_service::ProxyService,
+        snapshot_service::SnapshotService, upstream_watcher::UpstreamWatcher,
+        watch_service::WatchService, webhook_dispatcher::WebhookDispatcher,
+    },
+    system::{SystemConfig, build_http_client, build_webhook_client},
     utils::errors::AppResult,
 };
-use reqwest::Client;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -15,6 +20,13 @@ pub struct AppState {
     pub runtime_stats: Arc<RuntimeStats>,
     pub cache_service: Arc<CacheService>,
     pub proxy_service: Arc<ProxyService>,
+    pub history_service: Arc<HistoryService>,
+    pub override_service: Arc<OverrideService>,
+    pub audit_service: Arc<AuditService>,
+    pub change_notifier: Arc<ChangeNotifier>,
+    pub watch_service: Arc<WatchService>,
+    pub monitor_service: Arc<MonitorService>,
+    pub offline_mode: Arc<OfflineMode>,
 }
 
 impl AppState {
@@ -29,19 +41,110 @@ impl AppState {
         );
 
         // 创建Redis Repository
-        let redis_repo = Arc::new(RedisRepository::new(&redis_url)?);
+        let redis_repo = Arc::new(RedisRepository::new(
+            &redis_url,
+            config.redis.compression.clone(),
+        )?);
 
         // 测试Redis连接
         redis_repo.ping().await?;
 
         // 创建服务
-        let cache_service = Arc::new(CacheService::new(redis_repo));
-        let http_client = Client::new();
+        let change_notifier = Arc::new(ChangeNotifier::new(
+            redis_repo.clone(),
+            config.notifications.clone(),
+            config
+                .redis
+                .stale_expire_time
+                .max(config.redis.cache_expire_time),
+        ));
+        change_notifier.start(&redis_url)?;
+        let audit_service = Arc::new(AuditService::new(
+            redis_repo.clone(),
+            config.admin.audit_log_size,
+        ));
+        let override_service = Arc::new(OverrideService::new(
+            redis_repo.clone(),
+            audit_service.clone(),
+            change_notifier.clone(),
+        ));
+        let offline_mode = Arc::new(OfflineMode::new(
+            redis_repo.clone(),
+            audit_service.clone(),
+            config.offline,
+            config.redis.cache_expire_time,
+            config.redis.stale_expire_time,
+        ));
+        offline_mode.sync().await?;
+        offline_mode.start();
+        let snapshot_repo = if config.snapshot.enabled {
+            Some(Arc::new(SnapshotRepository::open(
+                &config.snapshot.directory,
+            )?))
+        } else {
+            None
+        };
+        if let Some(snapshot_repo) = &snapshot_repo {
+            let snapshot_service = Arc::new(SnapshotService::new(
+                redis_repo.clone(),
+                snapshot_repo.clone(),
+                config.snapshot.clone(),
+                config.redis.stale_expire_time,
+            ));
+            if config.snapshot.seed_on_startup {
+                let seeded = snapshot_service.seed().await?;
+                tracing::info!("Seeded {} cache entries from disk snapshot", seeded);
+            }
+            snapshot_service.start();
+        }
+        let cache_service = Arc::new(CacheService::new(
+            redis_repo.clone(),
+            config.redis.stale_expire_time,
+            config.history.clone(),
+            snapshot_repo,
+        ));
+        // 外部 Webhook 使用独立的客户端，代理请求与版本固定共用同一个推送器
+        let webhooks = Arc::new(WebhookDispatcher::new(
+            &config.outbound_webhooks,
+            build_webhook_client(&config.http_client)?,
+            cache_service.clone(),
+        )?);
+        let history_service = Arc::new(HistoryService::new(
+            redis_repo,
+            config.history.clone(),
+            audit_service.clone(),
+            change_notifier.clone(),
+            webhooks.clone(),
+        ));
+        let http_client = build_http_client(&config.http_client)?;
         let proxy_service = Arc::new(ProxyService::new(
             cache_service.clone(),
             http_client,
-            config.proxy_address.clone(),
-            config.redis.cache_expire_time,
+            runtime_stats.clone(),
+            change_notifier.clone(),
+            offline_mode.clone(),
+            webhooks,
+            &config,
+        )?);
+        let upstream_watcher = Arc::new(UpstreamWatcher::new(
+            proxy_service.upstreams(),
+            cache_service.clone(),
+            change_notifier.clone(),
+            offline_mode.clone(),
+        ));
+        upstream_watcher.start();
+        let watch_service = Arc::new(WatchService::new(
+            proxy_service.clone(),
+            cache_service.clone(),
+            override_service.clone(),
+            change_notifier.clone(),
+        ));
+        let monitor_service = Arc::new(MonitorService::new(
+            cache_service.clone(),
+            proxy_service.clone(),
+            change_notifier.clone(),
+            offline_mode.clone(),
+            config.webhook.clone(),
         ));
 
         Ok(Self {
@@ -49,6 +152,13 @@ impl AppState {
             runtime_stats,
             cache_service,
             proxy_service,
+            history_service,
+            override_service,
+            audit_service,
+            change_notifier,
+            watch_service,
+            monitor_service,
+            offline_mode,
         })
     }
 }
@@ -0,0 +1,12 @@
+use crate::system::RetryConfig;
+use rand::Rng;
+use std::time::Duration;
+
+/// 计算第 attempt 次重试前的退避时间（full jitter）
+pub fn backoff_delay(retry: &RetryConfig, attempt: u32) -> Duration {
+    let exp = retry
+        .initial_backoff_ms
+        .saturating_mul(1u64 << attempt.min(16));
+    let cap = exp.min(retry.max_backoff_ms);
+    Duration::from_millis(rand::rng().random_range(0..=cap))
+}
@@ -0,0 +1,233 @@
+use crate::utils::errors::{AppError, AppResult};
+use bytes::Bytes;
+use flate2::{Compression as GzipLevel, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// 存储格式头：固定的魔数后跟一个编码字节。魔数以 NUL 开头，
+// 不会与文本配置内容的开头混淆；没有格式头的值视为未压缩。
+// 编码字节的最高位表示其后跟着一个长度字节与原始内容的哈希
+const STORED_MAGIC: &[u8] = b"\0CCP";
+const HEADER_NONE: u8 = 0x00;
+const HEADER_ZSTD: u8 = 0x01;
+const HEADER_GZIP: u8 = 0x02;
+const HEADER_HASH_FLAG: u8 = 0x80;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    /// 对应的 HTTP Content-Encoding 取值
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Zstd => Some("zstd"),
+            Compression::Gzip => Some("gzip"),
+        }
+    }
+
+    fn header_byte(self) -> u8 {
+        match self {
+            Compression::None => HEADER_NONE,
+            Compression::Zstd => HEADER_ZSTD,
+            Compression::Gzip => HEADER_GZIP,
+        }
+    }
+
+    pub fn compress(self, data: &[u8], level: i32) -> AppResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zstd => zstd::encode_all(data, level)
+                .map_err(|e| AppError::Cache(format!("zstd compression failed: {}", e))),
+            Compression::Gzip => {
+                let level = GzipLevel::new(level.clamp(0, 9) as u32);
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder
+                    .write_all(data)
+                    .and_then(|_| encoder.finish())
+                    .map_err(|e| AppError::Cache(format!("gzip compression failed: {}", e)))
+            }
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> AppResult<Bytes> {
+        match self {
+            Compression::None => Ok(Bytes::copy_from_slice(data)),
+            Compression::Zstd => zstd::decode_all(data)
+                .map(Bytes::from)
+                .map_err(|e| AppError::Cache(format!("zstd decompression failed: {}", e))),
+            Compression::Gzip => {
+                let mut decoded = Vec::new();
+                GzDecoder::new(data)
+                    .read_to_end(&mut decoded)
+                    .map_err(|e| AppError::Cache(format!("gzip decompression failed: {}", e)))?;
+                Ok(Bytes::from(decoded))
+            }
+        }
+    }
+}
+
+/// 带编码信息的响应体，压缩存储的缓存值可不经解压直接返回给支持该编码的客户端
+#[derive(Clone)]
+pub struct EncodedBody {
+    pub encoding: Compression,
+    pub data: Bytes,
+    /// 原始内容的哈希，写入缓存时一并保存，命中时无需解压计算；未知时为空
+    pub hash: Option<String>,
+}
+
+impl EncodedBody {
+    pub fn identity(data: Bytes) -> Self {
+        Self {
+            encoding: Compression::None,
+            data,
+            hash: None,
+        }
+    }
+
+    /// 解码为原始内容
+    pub fn decode(self) -> AppResult<Bytes> {
+        match self.encoding {
+            Compression::None => Ok(self.data),
+            encoding => encoding.decompress(&self.data),
+        }
+    }
+
+    /// 编码为带格式头的存储格式
+    pub fn to_stored(&self) -> Vec<u8> {
+        let hash = self
+            .hash
+            .as_deref()
+            .filter(|hash| hash.len() <= u8::MAX as usize);
+        let hash_len = hash.map_or(0, |hash| hash.len() + 1);
+        let mut stored = Vec::with_capacity(STORED_MAGIC.len() + 1 + hash_len + self.data.len());
+        stored.extend_from_slice(STORED_MAGIC);
+        match hash {
+            Some(hash) => {
+                stored.push(self.encoding.header_byte() | HEADER_HASH_FLAG);
+                stored.push(hash.len() as u8);
+                stored.extend_from_slice(hash.as_bytes());
+            }
+            None => stored.push(self.encoding.header_byte()),
+        }
+        stored.extend_from_slice(&self.data);
+        stored
+    }
+
+    /// 从存储格式解析，没有格式头的值按未压缩内容返回
+    pub fn from_stored(stored: Bytes) -> Self {
+        let mut header_len = STORED_MAGIC.len() + 1;
+        if !stored.starts_with(STORED_MAGIC) || stored.len() < header_len {
+            return Self::identity(stored);
+        }
+        let header = stored[STORED_MAGIC.len()];
+        let encoding = match header & !HEADER_HASH_FLAG {
+            HEADER_NONE => Compression::None,
+            HEADER_ZSTD => Compression::Zstd,
+            HEADER_GZIP => Compression::Gzip,
+            _ => return Self::identity(stored),
+        };
+        let mut hash = None;
+        if header & HEADER_HASH_FLAG != 0 {
+            let Some(&len) = stored.get(header_len) else {
+                return Self::identity(stored);
+            };
+            let Some(Ok(value)) = stored
+                .get(header_len + 1..header_len + 1 + len as usize)
+                .map(std::str::from_utf8)
+            else {
+                return Self::identity(stored);
+            };
+            hash = Some(value.to_string());
+            header_len += 1 + len as usize;
+        }
+        Self {
+            encoding,
+            data: stored.slice(header_len..),
+            hash,
+        }
+    }
+}
+
+/// 判断客户端的 Accept-Encoding 是否接受指定编码，显式列出的编码优先于通配符
+pub fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    let mut wildcard = false;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let token = parts.next().unwrap_or_default().trim();
+        let accepted = !parts.any(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q <= 0.0)
+        });
+        if token.eq_ignore_ascii_case(encoding) {
+            return accepted;
+        }
+        if token == "*" {
+            wildcard = accepted;
+        }
+    }
+    wildcard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_stored_values() {
+        for encoding in [Compression::None, Compression::Zstd, Compression::Gzip] {
+            let content = b"spring:\n  profiles: prod\n";
+            let body = EncodedBody {
+                encoding,
+                data: Bytes::from(encoding.compress(content, 3).unwrap()),
+                hash: None,
+            };
+            let parsed = EncodedBody::from_stored(Bytes::from(body.to_stored()));
+            assert_eq!(parsed.encoding, encoding);
+            assert!(parsed.hash.is_none());
+            assert_eq!(parsed.decode().unwrap(), &content[..]);
+        }
+    }
+
+    #[test]
+    fn round_trips_stored_hash() {
+        for encoding in [Compression::None, Compression::Zstd] {
+            let content = b"spring:\n  profiles: prod\n";
+            let body = EncodedBody {
+                encoding,
+                data: Bytes::from(encoding.compress(content, 3).unwrap()),
+                hash: Some("0123456789abcdef".to_string()),
+            };
+            let parsed = EncodedBody::from_stored(Bytes::from(body.to_stored()));
+            assert_eq!(parsed.encoding, encoding);
+            assert_eq!(parsed.hash.as_deref(), Some("0123456789abcdef"));
+            assert_eq!(parsed.decode().unwrap(), &content[..]);
+        }
+    }
+
+    #[test]
+    fn treats_values_without_header_as_identity() {
+        for stored in [
+            &b"\x01key: value"[..],
+            b"\x00",
+            b"\0CCP",
+            b"\0CCP\x09x",
+            b"\0CCP\x80",
+            b"\0CCP\x80\x10abc",
+            b"",
+        ] {
+            let parsed = EncodedBody::from_stored(Bytes::copy_from_slice(stored));
+            assert_eq!(parsed.encoding, Compression::None);
+            assert_eq!(parsed.data, stored);
+        }
+    }
+}
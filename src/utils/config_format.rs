@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// 配置文件格式，按请求路径的扩展名识别
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Properties,
+}
+
+impl ConfigFormat {
+    /// 根据路径（可带查询串）的扩展名识别格式
+    pub fn from_path(path: &str) -> Option<Self> {
+        let file_path = path.split('?').next().unwrap_or_default();
+        let (_, ext) = file_path.rsplit_once('.')?;
+        match ext.to_ascii_lowercase().as_str() {
+            "yml" | "yaml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "properties" => Some(Self::Properties),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Yaml => "text/plain; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Properties => "text/plain; charset=utf-8",
+        }
+    }
+}
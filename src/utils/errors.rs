@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
@@ -37,6 +37,22 @@ pub enum AppError {
     
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Service unavailable: {message}")]
+    ServiceUnavailable {
+        message: String,
+        // 建议客户端重试的等待秒数
+        retry_after: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -59,12 +75,20 @@ impl AppError {
             AppError::Proxy(_) => "PROXY_ERROR",
             AppError::Internal(_) => "INTERNAL_ERROR",
             AppError::NotFound(_) => "NOT_FOUND",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::ServiceUnavailable { .. } => "SERVICE_UNAVAILABLE",
         }
     }
 
     pub fn status_code(&self) -> StatusCode {
         match self {
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
             AppError::Config(_) => StatusCode::BAD_REQUEST,
             AppError::Proxy(_) => StatusCode::BAD_GATEWAY,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
@@ -84,7 +108,17 @@ impl IntoResponse for AppError {
         
         tracing::error!("Application error: {} (code: {})", self, self.error_code());
         
-        (status, Json(error_response)).into_response()
+        let mut response = (status, Json(error_response)).into_response();
+        if let AppError::ServiceUnavailable {
+            retry_after: Some(seconds),
+            ..
+        } = self
+        {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
@@ -1 +1,4 @@
-pub mod errors; 
\ No newline at end of file
+pub mod backoff;
+pub mod compression;
+pub mod config_format;
+pub mod errors;